@@ -1,9 +1,15 @@
 //! CLI options
 
+use crate::commands::Space;
+use crate::commands::export::OnConflict;
+use crate::commands::list::path_mapping::PathMapping;
 use crate::http::Url;
+use crate::output::OutputFormat;
+use crate::secrets::SecretBackend;
 use anyhow::{Result, bail};
 pub use clap::Parser;
-use clap::Subcommand;
+use clap::{ArgGroup, Args, Subcommand};
+use std::path::PathBuf;
 use std::time::Duration;
 
 /// syno-photos-util
@@ -20,12 +26,44 @@ pub struct Cli {
     /// HTTP request timeout in seconds
     ///
     /// Must be greater or equal to 5. When Synology Photos does not respond within the timeout, an
-    /// error is displayed. Try to increase the value for slow connections
+    /// error is displayed. Try to increase the value for slow connections. The timeout applies to
+    /// each read, so large downloads are not interrupted while data keeps flowing
     #[arg(
         long = "timeout",
         default_value = "30",
         value_parser = try_parse_duration)]
     pub timeout_seconds: Duration,
+
+    /// Output format of list, list-albums, list-items, where, orphans, duplicates, tasks status,
+    /// tasks wait and status commands
+    ///
+    /// Other commands only print text and fail with a different format. In machine-readable
+    /// formats, errors are reported as record fields (e.g. photo locations
+    /// which are not accessible) or on standard error
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub output: OutputFormat,
+
+    /// Name of the profile to use instead of the active one
+    ///
+    /// Each profile has its own session, device ids and settings, e.g. to work with multiple NAS
+    /// boxes. See the 'profiles' command
+    #[arg(long, global = true, env = "SYNO_PHOTOS_UTIL_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Sign in again when the session has expired, e.g. in scheduled jobs
+    ///
+    /// Credentials are read from SYNO_PHOTOS_UTIL_USER and SYNO_PHOTOS_UTIL_PASSWORD environment
+    /// variables, the file given by '--credentials-file', or the password stored with 'login
+    /// --save-password', in this order. When 2-factor authentication is enabled, the device needs
+    /// to be remembered with 'login --remember'
+    #[arg(long, global = true, env = "SYNO_PHOTOS_UTIL_RELOGIN")]
+    pub relogin: bool,
+
+    /// File with DSM user name in the first line and password in the second one
+    ///
+    /// Used by '--relogin'. The file should only be readable by its owner
+    #[arg(long, global = true, value_name = "PATH", requires = "relogin")]
+    pub credentials_file: Option<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -60,6 +98,20 @@ pub enum Command {
         /// Writes device id to $HOME/.syno-photo-util file
         #[arg(long)]
         remember: bool,
+
+        /// Keep session key and device ids in a secret store instead of $HOME/.syno-photo-util
+        ///
+        /// The choice is remembered for the profile
+        #[arg(long, value_enum)]
+        secret_store: Option<SecretBackend>,
+
+        /// Store DSM password in the secret store
+        ///
+        /// Allows signing in again without user interaction, e.g. in scheduled jobs, when 'login'
+        /// is run without --user and --password. Requires --secret-store (on this or previous
+        /// login)
+        #[arg(long)]
+        save_password: bool,
     },
 
     /// List albums accessible by the currently logged-in user
@@ -81,42 +133,424 @@ pub enum Command {
 
     /// List file locations (folders) of photos in an album
     List {
-        /// Album name; can also be a person name in "People" auto-album
+        /// Album name; can also be a person name in "People" or a place name in "Places"
+        /// auto-album
         album_name: String,
+
+        /// Rewrite location of Personal or Shared Space in printed paths
+        ///
+        /// In the form of SPACE[:USER]=PREFIX, where SPACE is "personal" or "shared", and
+        /// optional USER limits the rule to Personal Space of a single user. "{user}" in PREFIX
+        /// gets replaced with owner name, e.g. "personal=/mnt/nas/homes/{user}/Photos" or
+        /// "shared=\\nas\photo". Can be specified multiple times; the most specific rule applies
+        #[arg(long = "map-path", value_name = "RULE")]
+        path_mappings: Vec<PathMapping>,
+
+        /// Print real /volumeN locations instead of /var/services links
+        ///
+        /// The API does not tell on which volume the homes shared folder is located, so Personal
+        /// Space is assumed to be located on /volume1 unless --homes-path is given
+        #[arg(long)]
+        volume_paths: bool,
+
+        /// Location of the homes shared folder printed with --volume-paths, e.g. /volume2/homes
+        #[arg(long, value_name = "PATH")]
+        homes_path: Option<String>,
+
+        /// Save --map-path rules, --volume-paths and --homes-path in $HOME/.syno-photo-util file
+        ///
+        /// Saved settings are used on future runs, combined with the ones given on command line.
+        /// Run with --save alone to clear them
+        #[arg(long)]
+        save: bool,
     },
 
-    /// Export (accessible) album photos to a folder in the user's Personal Space
+    /// List photos in an album with their metadata
     ///
-    /// Requires that home service is enabled on DSM
-    Export {
-        /// Album name; can be a person name in "People" auto-album
+    /// Prints item id, time taken, file size in bytes, dimensions, type (photo, video or live)
+    /// and owner of each item
+    ListItems {
+        /// Album name; can also be a person name in "People" or a place name in "Places"
+        /// auto-album
         album_name: String,
+    },
+
+    /// Find albums which contain a photo
+    ///
+    /// Reports owned and shared albums, and people in "People" auto-album. Album contents are
+    /// cached in $HOME/.syno-photos-util-cache file, so that repeated queries are faster
+    Where {
+        /// NAS path (as printed by the 'list' command), item id (as printed by 'list-items') or
+        /// file name pattern with '*' and '?' wildcards
+        query: String,
+
+        /// Ignore cached album contents and list all albums again
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// List locations of photos which are not in any album
+    ///
+    /// Looks in the whole timeline of Personal and Shared Space, or in a single folder. Paths are
+    /// resolved like in the 'list' command, using the settings saved with 'list --save'. Album
+    /// contents are cached in $HOME/.syno-photos-util-cache file
+    Orphans {
+        /// Folder path to look in, instead of the whole timeline
+        folder_path: Option<String>,
+
+        /// Look for the folder in Shared Space
+        #[arg(long, requires = "folder_path")]
+        shared: bool,
+
+        /// Include photos in subfolders of the folder
+        #[arg(short, long, requires = "folder_path")]
+        recursive: bool,
+
+        /// Do not report photos of people in "People" auto-album
+        #[arg(long)]
+        exclude_people: bool,
+
+        /// Ignore cached album contents and list all albums again
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Find photos copied to multiple locations
+    ///
+    /// Photos with the same file name, size and time taken in the scanned albums and folders are
+    /// grouped and printed with their locations (resolved like in the 'list' command)
+    #[command(group(
+        ArgGroup::new("source")
+            .required(true)
+            .multiple(true)
+            .args(["albums", "folders", "shared_folders"])
+    ))]
+    Duplicates {
+        /// Album to scan; can also be a person name in "People" or a place name in "Places"
+        /// auto-album. Can be specified multiple times
+        #[arg(long = "album", value_name = "ALBUM_NAME")]
+        albums: Vec<String>,
+
+        /// Folder in Personal Space to scan. Can be specified multiple times
+        #[arg(long = "folder", value_name = "FOLDER_PATH")]
+        folders: Vec<String>,
+
+        /// Folder in Shared Space to scan. Can be specified multiple times
+        #[arg(long = "shared-folder", value_name = "FOLDER_PATH")]
+        shared_folders: Vec<String>,
 
-        /// Create target folder if it does not exist in Personal Space
+        /// Include subfolders of the folders
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Compare content instead of file name and time taken
+        ///
+        /// Photos with the same size are downloaded to compute hashes of their content. This
+        /// finds renamed copies too, but can take a long time
+        #[arg(long)]
+        hash: bool,
+    },
+
+    /// Create, rename or delete albums
+    ///
+    /// Albums are looked up by name, like in other commands
+    Album {
+        #[command(subcommand)]
+        command: AlbumCommand,
+    },
+
+    /// Export (accessible) album photos to a folder in the user's Personal Space or Shared Space
+    ///
+    /// Exporting to Personal Space requires that home service is enabled on DSM. Exporting to
+    /// Shared Space requires permission to upload to it
+    Export {
+        /// Album names; can be a person name in "People" or a place name in "Places" auto-album
+        ///
+        /// Names with '*' and '?' wildcards prefixed with 'glob:' (e.g. 'glob:holidays *'), or
+        /// regular expressions prefixed with 're:' (e.g. 're:^holidays 20\d\d$'), match owned and
+        /// shared albums, ignoring case; other names are taken literally. With several albums,
+        /// each one is exported to its own subfolder of the target folder, named after the album;
+        /// albums sharing a name cannot be exported together
+        #[arg(required = true)]
+        album_names: Vec<String>,
+
+        /// Create target folder if it does not exist
         #[arg(long)]
         create: bool,
 
-        /// Folder name in user's Personal Space
+        /// Folder name in the target space
         folder_path: String,
+
+        /// Space of the target folder
+        #[arg(long, value_enum, default_value_t)]
+        space: Space,
+
+        /// What to do with photos already present in the target folder
+        #[arg(long, value_enum, default_value_t)]
+        on_conflict: OnConflict,
+
+        /// Copy only items added to the album since the previous run with --sync
+        ///
+        /// Exported items are recorded per album and target folder in
+        /// $HOME/.syno-photos-util-sync file. Items removed from the album since the previous run
+        /// are reported
+        #[arg(long)]
+        sync: bool,
+
+        /// Delete items removed from the album since the previous run from the target folder
+        ///
+        /// Only copies recorded by previous --sync runs are deleted, never files with a name
+        /// which an item still in the album has
+        #[arg(long, requires = "sync")]
+        prune: bool,
+
+        /// Report what would be copied without creating folders or scheduling copy tasks
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Print ids of the scheduled copy tasks and exit without waiting for them to finish
+        ///
+        /// Use the 'tasks' command to follow them later
+        #[arg(long, conflicts_with = "sync")]
+        detach: bool,
+    },
+
+    /// Run export jobs listed in a TOML file
+    ///
+    /// Each job gives an album (or person) name, a target folder, and optionally its space
+    /// ("personal" or "shared"), conflict policy ("skip", "overwrite" or "rename") and whether to
+    /// create the folder. A report of all jobs is printed at the end; the command fails when any
+    /// job failed, including jobs with items which failed to copy or were canceled
+    RunJobs {
+        /// TOML file with [[jobs]] tables, see README for an example; a file with .json extension
+        /// is read as JSON with a "jobs" array instead
+        jobs_file: PathBuf,
+
+        /// Run a few jobs at once; the output of each job is printed when it finishes
+        #[arg(long)]
+        parallel: bool,
+    },
+
+    /// Show status of, wait for or cancel background copy tasks scheduled by export
+    Tasks {
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+
+    /// Download (accessible) album photos to a local directory
+    ///
+    /// Original files are downloaded keeping their names and modification times. Files already
+    /// present in the directory are skipped
+    Download {
+        /// Album name; can be a person name in "People" or a place name in "Places" auto-album
+        album_name: String,
+
+        /// Local directory; created if it does not exist
+        directory: PathBuf,
     },
 
     /// Sign out of DSM
     ///
-    /// Removes session key from $HOME/.syno-photo-util file
+    /// Invalidates the session on DSM and removes session key from $HOME/.syno-photo-util file
     Logout {
         /// Enforce OTP verification on future runs
         ///
         /// Only applicable when 2-factor authentication is enabled on user account.
-        /// Removes ALL device ids from $HOME/.syno-photo-util file
+        /// Removes ALL device ids from $HOME/.syno-photo-util file, as well as the password stored
+        /// in the secret store
         #[arg(long)]
         forget: bool,
+
+        /// Do not sign out on DSM, only remove session key locally
+        ///
+        /// By default, the session is also invalidated on DSM
+        #[arg(long)]
+        local_only: bool,
     },
 
     /// Check DSM sign-in status
+    ///
+    /// Verifies that the session is still valid on DSM, and shows whether Personal and Shared Space
+    /// are available to the user
     Status,
 
     /// Check if new version is available
     CheckUpdate,
+
+    /// Manage profiles stored in $HOME/.syno-photo-util file
+    ///
+    /// Lists profiles when no subcommand is given
+    Profiles {
+        #[command(subcommand)]
+        command: Option<ProfilesCommand>,
+    },
+}
+
+impl Command {
+    /// Whether the command writes its results in the format given by '--output'. Other commands
+    /// only print text.
+    pub fn supports_output_format(&self) -> bool {
+        matches!(
+            self,
+            Command::ListAlbums { .. }
+                | Command::List { .. }
+                | Command::ListItems { .. }
+                | Command::Where { .. }
+                | Command::Orphans { .. }
+                | Command::Duplicates { .. }
+                | Command::Tasks {
+                    command: TasksCommand::Status { .. } | TasksCommand::Wait { .. }
+                }
+                | Command::Status
+        )
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfilesCommand {
+    /// List profiles, marking the active one with '*'
+    List,
+
+    /// Make a profile active; used when --profile is not specified
+    ///
+    /// The profile is created if it does not exist, use the 'login' command to sign in with it
+    Switch {
+        /// Profile name
+        name: String,
+    },
+
+    /// Remove a profile, including its session and device ids
+    ///
+    /// Does not sign out of DSM; use the 'logout' command first for that
+    Remove {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TasksCommand {
+    /// Show status of tasks
+    Status {
+        /// Task ids, as printed by 'export --detach'
+        #[arg(required = true)]
+        task_ids: Vec<u32>,
+    },
+
+    /// Wait for tasks to finish and show their final status
+    Wait {
+        /// Task ids, as printed by 'export --detach'
+        #[arg(required = true)]
+        task_ids: Vec<u32>,
+    },
+
+    /// Cancel tasks; items copied so far are kept
+    Cancel {
+        /// Task ids, as printed by 'export --detach'
+        #[arg(required = true)]
+        task_ids: Vec<u32>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AlbumCommand {
+    /// Create an empty album
+    Create {
+        /// Album name; should not be used by another album
+        name: String,
+    },
+
+    /// Rename an album
+    Rename {
+        /// Current album name
+        album_name: String,
+
+        /// New album name; should not be used by another album
+        new_name: String,
+    },
+
+    /// Add photos to an album
+    ///
+    /// Photos are given as item ids, or taken from another album, a folder or a file. Photos
+    /// already in the album are skipped
+    Add {
+        /// Name of the album to add photos to
+        album_name: String,
+
+        #[command(flatten)]
+        items: ItemSource,
+    },
+
+    /// Remove photos from an album
+    ///
+    /// Photos are given as item ids, or taken from another album, a folder or a file. The photos
+    /// themselves are not deleted
+    Remove {
+        /// Name of the album to remove photos from
+        album_name: String,
+
+        #[command(flatten)]
+        items: ItemSource,
+    },
+
+    /// Create an album from photos in a folder, or update an existing one
+    ///
+    /// Photos in the folder which are missing in the album are added. This is the reverse of
+    /// the 'export' command
+    FromFolder {
+        /// Album name; the album is created if it does not exist
+        album_name: String,
+
+        /// Folder path in Personal Space, or in Shared Space with --shared
+        folder_path: String,
+
+        /// Look for the folder in Shared Space
+        #[arg(long)]
+        shared: bool,
+
+        /// Include photos in subfolders
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Remove photos which are not in the folder from the album
+        #[arg(long)]
+        prune: bool,
+    },
+
+    /// Delete an album
+    ///
+    /// Photos in the album are not deleted. Asks for confirmation unless --yes is specified
+    Delete {
+        /// Album name
+        album_name: String,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Photos to add to or remove from an album
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+pub struct ItemSource {
+    /// Item ids, as printed by the 'list-items' command
+    pub item_ids: Vec<u32>,
+
+    /// Take photos of another album; can also be a person name in "People" or a place name in
+    /// "Places" auto-album
+    #[arg(long, value_name = "ALBUM_NAME")]
+    pub from_album: Option<String>,
+
+    /// Take photos of a folder in Personal Space, not including subfolders
+    #[arg(long, value_name = "FOLDER_PATH")]
+    pub from_folder: Option<String>,
+
+    /// Take photos from a file of NAS paths, one per line, as printed by the 'list' command
+    ///
+    /// Path mapping rules and volume paths mode saved with 'list --save' are taken into account
+    #[arg(long, value_name = "PATH")]
+    pub from_file: Option<PathBuf>,
 }
 
 fn try_parse_duration(arg: &str) -> Result<Duration> {
@@ -133,3 +567,18 @@ fn verify_cli() {
     use clap::CommandFactory;
     Cli::command().debug_assert()
 }
+
+#[test]
+fn output_format_is_supported_by_listing_commands_only() {
+    let supports = |args: &[&str]| {
+        Cli::try_parse_from([&["syno-photos-util"], args].concat())
+            .unwrap()
+            .command
+            .supports_output_format()
+    };
+    assert!(supports(&["list", "My Album", "--output", "csv"]));
+    assert!(supports(&["tasks", "wait", "1", "2"]));
+    assert!(!supports(&["tasks", "cancel", "1"]));
+    assert!(!supports(&["export", "My Album", "/export"]));
+    assert!(!supports(&["download", "My Album", "/tmp/album"]));
+}
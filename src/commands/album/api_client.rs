@@ -0,0 +1,85 @@
+//! Extra methods for SessionClient used by album command
+
+use crate::commands::api_client::{ApiClient, ApiParams, SessionClient};
+use anyhow::Result;
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+use syno_api::foto::{self, browse::album::dto::Album};
+
+const NORMAL_ALBUM_API: &str = "SYNO.Foto.Browse.NormalAlbum";
+
+impl<C: ApiClient> SessionClient<'_, C> {
+    pub async fn create_album(&self, name: &str) -> Result<Album> {
+        #[derive(Debug, Deserialize)]
+        struct AlbumContainer {
+            album: Album,
+        }
+
+        let album: AlbumContainer = self
+            .client
+            .post(
+                self.dsm_url.clone(),
+                ApiParams::new(NORMAL_ALBUM_API, "create", 1),
+                &[("name", serde_json::to_string(name)?.as_str())],
+            )
+            .await?;
+        Ok(album.album)
+    }
+
+    pub async fn rename_album(&self, album_id: u32, name: &str) -> Result<()> {
+        let _: IgnoredAny = self
+            .client
+            .post(
+                self.dsm_url.clone(),
+                ApiParams::new(foto::browse::album::API, "set_name", 1),
+                &[
+                    ("id", album_id.to_string().as_str()),
+                    ("name", serde_json::to_string(name)?.as_str()),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Photos in the album are not deleted
+    pub async fn delete_album(&self, album_id: u32) -> Result<()> {
+        let _: IgnoredAny = self
+            .client
+            .post(
+                self.dsm_url.clone(),
+                ApiParams::new(foto::browse::album::API, "delete", 1),
+                &[("id", format!("[{album_id}]").as_str())],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn add_album_items(&self, album_id: u32, item_ids: &[u32]) -> Result<()> {
+        self.set_album_items(album_id, item_ids, "add_item").await
+    }
+
+    pub async fn remove_album_items(&self, album_id: u32, item_ids: &[u32]) -> Result<()> {
+        self.set_album_items(album_id, item_ids, "remove_item")
+            .await
+    }
+
+    async fn set_album_items(&self, album_id: u32, item_ids: &[u32], method: &str) -> Result<()> {
+        let ids = item_ids
+            .iter()
+            .map(u32::to_string)
+            .reduce(|acc, id| format!("{acc},{id}"))
+            .expect("item_ids should not be empty");
+        let _: IgnoredAny = self
+            .client
+            .post(
+                self.dsm_url.clone(),
+                ApiParams::new(NORMAL_ALBUM_API, method, 1),
+                &[
+                    ("id", album_id.to_string().as_str()),
+                    ("item", format!("[{ids}]").as_str()),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,482 @@
+use crate::cli::{AlbumCommand, ItemSource};
+use crate::commands::api_client::{ApiClient, PAGE_SIZE, SessionClient, Space};
+use crate::commands::list::path_mapping::PathMapper;
+use crate::commands::{Album, DsmError, find_album, find_in_pages, list_folder_tree};
+use crate::conf::Conf;
+use crate::fs::Fs;
+use crate::http::HttpClient;
+use crate::io::{Io, read_input};
+use anyhow::{Result, anyhow, bail};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use syno_api::foto::browse::album::dto::Album as AlbumDto;
+use syno_api::foto::error::PhotoError;
+
+mod api_client;
+
+/// Number of items added to or removed from an album in a single request
+const BATCH_SIZE: usize = 100;
+
+pub async fn handle<C: HttpClient, I: Io, F: Fs>(
+    command: AlbumCommand,
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+    fs: &F,
+) -> Result<()> {
+    if !conf.is_logged_in() {
+        bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
+    }
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
+    match command {
+        AlbumCommand::Create { name } => {
+            check_name_available(name.as_str(), None, &client).await?;
+            let album = client.create_album(name.trim()).await?;
+            writeln!(io.stdout(), "Created album '{}'", album.name)?;
+        }
+        AlbumCommand::Rename {
+            album_name,
+            new_name,
+        } => {
+            let album = get_owned_album(album_name.as_str(), &client).await?;
+            check_name_available(new_name.as_str(), Some(album.id), &client).await?;
+            client.rename_album(album.id, new_name.trim()).await?;
+            writeln!(
+                io.stdout(),
+                "Renamed album '{}' to '{}'",
+                album.name,
+                new_name.trim()
+            )?;
+        }
+        AlbumCommand::Add { album_name, items } => {
+            update_items(album_name.as_str(), (items, true), conf, &client, io, fs).await?;
+        }
+        AlbumCommand::Remove { album_name, items } => {
+            update_items(album_name.as_str(), (items, false), conf, &client, io, fs).await?;
+        }
+        AlbumCommand::FromFolder {
+            album_name,
+            folder_path,
+            shared,
+            recursive,
+            prune,
+        } => {
+            let space = if shared {
+                Space::Shared
+            } else {
+                Space::Personal
+            };
+            let item_ids = list_folder_tree((folder_path.as_str(), space), recursive, &client)
+                .await?
+                .into_iter()
+                .map(|i| i.id)
+                .collect();
+            sync_album(album_name.as_str(), item_ids, prune, &client, io).await?;
+        }
+        AlbumCommand::Delete { album_name, yes } => {
+            let album = get_owned_album(album_name.as_str(), &client).await?;
+            if !yes && !confirm_delete(&album, io)? {
+                writeln!(io.stdout(), "Canceled")?;
+                return Ok(());
+            }
+            client.delete_album(album.id).await?;
+            writeln!(io.stdout(), "Deleted album '{}'", album.name)?;
+        }
+    }
+    Ok(())
+}
+
+/// Find an album owned by the user by name (case-insensitive). Albums shared with the user and
+/// auto-albums (people, places) cannot be changed, so they are not looked up.
+async fn find_owned_album<C: ApiClient>(
+    album_name: &str,
+    client: &SessionClient<'_, C>,
+) -> Result<Option<AlbumDto>> {
+    find_in_pages(
+        client.pages(PAGE_SIZE, |c, page| c.list_owned_albums(page)),
+        |a| a.name.eq_ignore_ascii_case(album_name),
+    )
+    .await
+}
+
+/// Like [find_owned_album], but fails when there is no such album
+async fn get_owned_album<C: ApiClient>(
+    album_name: &str,
+    client: &SessionClient<'_, C>,
+) -> Result<AlbumDto> {
+    find_owned_album(album_name, client)
+        .await?
+        .ok_or_else(|| anyhow!("no owned album named '{album_name}'"))
+}
+
+/// Add items given by `source` to the album, or remove them when `add` is false, in batches
+async fn update_items<C: ApiClient, I: Io, F: Fs>(
+    album_name: &str,
+    (source, add): (ItemSource, bool),
+    conf: &Conf,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+    fs: &F,
+) -> Result<()> {
+    let album = get_owned_album(album_name, client).await?;
+    let (item_ids, not_found) = resolve_items(source, conf, client, io, fs).await?;
+    let album_id = album.id;
+    let album_items: HashSet<u32> = client
+        .list_all_items(&Album::Normal(album))
+        .await?
+        .into_iter()
+        .map(|i| i.id)
+        .collect();
+    let (present, absent): (Vec<u32>, Vec<u32>) = item_ids
+        .into_iter()
+        .partition(|id| album_items.contains(id));
+    if add {
+        for batch in absent.chunks(BATCH_SIZE) {
+            client.add_album_items(album_id, batch).await?;
+        }
+        writeln!(
+            io.stdout(),
+            "Summary: {} added, {} already present, {not_found} not found",
+            absent.len(),
+            present.len()
+        )?;
+    } else {
+        for batch in present.chunks(BATCH_SIZE) {
+            client.remove_album_items(album_id, batch).await?;
+        }
+        writeln!(
+            io.stdout(),
+            "Summary: {} removed, {} not in album, {not_found} not found",
+            present.len(),
+            absent.len()
+        )?;
+    }
+    Ok(())
+}
+
+/// Item ids given by `source`, without duplicates. Returns them along with the number of paths
+/// in the file which could not be found.
+async fn resolve_items<C: ApiClient, I: Io, F: Fs>(
+    ItemSource {
+        item_ids,
+        from_album,
+        from_folder,
+        from_file,
+    }: ItemSource,
+    conf: &Conf,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+    fs: &F,
+) -> Result<(Vec<u32>, usize)> {
+    let (item_ids, not_found) = if let Some(album_name) = from_album {
+        let user_settings = client.get_user_settings().await?;
+        let team_space_settings = client.get_team_space_settings().await?;
+        let Some(album) =
+            find_album(&album_name, &user_settings, &team_space_settings, client).await?
+        else {
+            bail!("album '{album_name}' not found")
+        };
+        let items = client.list_all_items(&album).await?;
+        (items.into_iter().map(|i| i.id).collect(), 0)
+    } else if let Some(folder_path) = from_folder {
+        let items =
+            list_folder_tree((folder_path.as_str(), Space::Personal), false, client).await?;
+        (items.into_iter().map(|i| i.id).collect(), 0)
+    } else if let Some(path) = from_file {
+        let paths = fs
+            .read_to_string(&path)
+            .map_err(|e| anyhow!("unable to read {}: {e}", path.display()))?;
+        resolve_paths(&paths, conf, client, io).await?
+    } else {
+        (item_ids, 0)
+    };
+    let mut seen = HashSet::new();
+    let item_ids = item_ids.into_iter().filter(|id| seen.insert(*id)).collect();
+    Ok((item_ids, not_found))
+}
+
+/// Find item ids of NAS paths printed by list command, one per line. Error lines and paths
+/// outside of Shared Space and user's Personal Space are reported as not found.
+pub(super) async fn resolve_paths<C: ApiClient, I: Io>(
+    paths: &str,
+    conf: &Conf,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+) -> Result<(Vec<u32>, usize)> {
+    let user = client.get_user_name().await?;
+    let path_mapper = PathMapper::new(
+        conf.path_mappings.clone(),
+        (conf.volume_paths, conf.homes_path.clone()),
+    );
+    let mut not_found = vec![];
+    /* Group files by folder, so that each folder is listed once */
+    let mut folders: HashMap<(Space, String), Vec<(String, &str)>> = HashMap::new();
+    for path in paths.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match path_mapper.parse(path, &user) {
+            Some((space, folder, filename)) => folders
+                .entry((space, folder))
+                .or_default()
+                .push((filename, path)),
+            None => not_found.push(path),
+        }
+    }
+    let mut item_ids = vec![];
+    for ((space, folder_path), files) in folders {
+        let folder_items: HashMap<String, u32> = match client
+            .get_folder_by_name((folder_path.as_str(), space))
+            .await
+        {
+            Ok(folder) => client
+                .all_pages(PAGE_SIZE, |c, page| {
+                    c.list_folder_items((folder.id, space), page)
+                })
+                .await?
+                .into_iter()
+                .map(|i| (i.filename, i.id))
+                .collect(),
+            Err(error) => match error.downcast::<DsmError>()? {
+                DsmError::Photo(PhotoError::NoAccessOrNotFound) => HashMap::new(),
+                other => bail!(other),
+            },
+        };
+        for (filename, path) in files {
+            match folder_items.get(&filename) {
+                Some(id) => item_ids.push(*id),
+                None => not_found.push(path),
+            }
+        }
+    }
+    for path in &not_found {
+        writeln!(io.stderr(), "Not found: {path}")?;
+    }
+    Ok((item_ids, not_found.len()))
+}
+
+/// Make the owned album contain `item_ids`, creating it if needed. When `prune` is set, other
+/// items are removed from the album.
+async fn sync_album<C: ApiClient, I: Io>(
+    album_name: &str,
+    item_ids: Vec<u32>,
+    prune: bool,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+) -> Result<()> {
+    /* Albums shared with the user cannot be changed, a new one is created instead */
+    let (album_id, album_items) = match find_owned_album(album_name, client).await? {
+        Some(album) => {
+            let album_id = album.id;
+            let album_items: HashSet<u32> = client
+                .list_all_items(&Album::Normal(album))
+                .await?
+                .into_iter()
+                .map(|i| i.id)
+                .collect();
+            (album_id, album_items)
+        }
+        None => {
+            if album_name.trim().is_empty() {
+                bail!("album name should not be empty")
+            }
+            let album = client.create_album(album_name.trim()).await?;
+            writeln!(io.stdout(), "Created album '{}'", album.name)?;
+            (album.id, HashSet::new())
+        }
+    };
+    let item_ids: HashSet<u32> = item_ids.into_iter().collect();
+    let missing: Vec<u32> = item_ids.difference(&album_items).copied().collect();
+    for batch in missing.chunks(BATCH_SIZE) {
+        client.add_album_items(album_id, batch).await?;
+    }
+    let removed: Vec<u32> = if prune {
+        album_items.difference(&item_ids).copied().collect()
+    } else {
+        vec![]
+    };
+    for batch in removed.chunks(BATCH_SIZE) {
+        client.remove_album_items(album_id, batch).await?;
+    }
+    writeln!(
+        io.stdout(),
+        "Summary: {} added, {} already present, {} removed",
+        missing.len(),
+        item_ids.len() - missing.len(),
+        removed.len()
+    )?;
+    Ok(())
+}
+
+/// Albums are looked up by name, so names should be unique
+/// Fail when an album named `name` (case-insensitive) exists, other than the one with `own_id`
+/// being renamed, e.g. only changing the case of its name
+async fn check_name_available<C: ApiClient>(
+    name: &str,
+    own_id: Option<u32>,
+    client: &SessionClient<'_, C>,
+) -> Result<()> {
+    if name.trim().is_empty() {
+        bail!("album name should not be empty")
+    }
+    let user_settings = client.get_user_settings().await?;
+    let team_space_settings = client.get_team_space_settings().await?;
+    if let Some(Album::Normal(album)) =
+        find_album(name.trim(), &user_settings, &team_space_settings, client).await?
+    {
+        if Some(album.id) != own_id {
+            bail!("album '{}' already exists", album.name)
+        }
+    }
+    Ok(())
+}
+
+fn confirm_delete<I: Io>(album: &AlbumDto, io: &mut I) -> Result<bool> {
+    let answer = read_input(
+        format!(
+            "Delete album '{}' ({} items)? Photos in the album are not deleted [y/N]",
+            album.name, album.item_count
+        )
+        .as_str(),
+        io,
+    )?;
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+#[tokio::test]
+async fn get_owned_album_ignores_albums_shared_with_me() {
+    use crate::test::{FakeHttpClient, FakeResponse, logged_in_conf};
+    use syno_api::foto::browse::album::API as ALBUM_API;
+
+    let client = FakeHttpClient::new(|request| {
+        assert!(
+            request.is(ALBUM_API, "list"),
+            "only owned albums should be listed"
+        );
+        let albums: Vec<_> = ["Trip", "Holidays"]
+            .into_iter()
+            .enumerate()
+            .filter(|_| request.param("offset").unwrap() == "0")
+            .map(|(id, name)| AlbumDto {
+                id: id as u32 + 1,
+                name: name.to_string(),
+                ..AlbumDto::default()
+            })
+            .collect();
+        FakeResponse::data(serde_json::json!({ "list": albums }))
+    });
+    let conf = logged_in_conf();
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+
+    assert_eq!(get_owned_album("holidays", &client).await.unwrap().id, 2);
+    assert_eq!(
+        get_owned_album("Family", &client)
+            .await
+            .unwrap_err()
+            .to_string(),
+        "no owned album named 'Family'"
+    );
+}
+
+#[tokio::test]
+async fn sync_album_creates_own_album_instead_of_changing_shared_one() {
+    use crate::test::{FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use std::sync::{Arc, Mutex};
+    use syno_api::foto::browse::album::API as ALBUM_API;
+
+    let calls = Arc::new(Mutex::new(vec![]));
+    let recorded_calls = calls.clone();
+    let client = FakeHttpClient::new(move |request| {
+        let method = request.param("method").unwrap();
+        recorded_calls.lock().unwrap().push(method.clone());
+        match method.as_str() {
+            /* "Events" is only shared with the user, so it is not listed as owned */
+            "list" => {
+                assert!(request.is(ALBUM_API, "list"));
+                FakeResponse::data(serde_json::json!({ "list": [] }))
+            }
+            "create" => {
+                assert_eq!(request.param("name").unwrap(), r#""Events""#);
+                let album = AlbumDto {
+                    id: 7,
+                    name: "Events".to_string(),
+                    ..AlbumDto::default()
+                };
+                FakeResponse::data(serde_json::json!({ "album": album }))
+            }
+            "add_item" => {
+                assert_eq!(request.param("id").unwrap(), "7");
+                FakeResponse::data(())
+            }
+            other => panic!("unexpected {other} request"),
+        }
+    });
+    let conf = logged_in_conf();
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+    let mut io = FakeIo::new("");
+
+    sync_album("Events", vec![1, 2], false, &client, &mut io)
+        .await
+        .unwrap();
+
+    assert_eq!(*calls.lock().unwrap(), ["list", "create", "add_item"]);
+    assert!(io.stdout_str().contains("Summary: 2 added"));
+}
+
+#[tokio::test]
+async fn rename_album_changing_only_case() {
+    use crate::cli::AlbumCommand;
+    use crate::test::{FakeFs, FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use std::sync::{Arc, Mutex};
+    use syno_api::foto;
+    use syno_api::foto::setting::team_space::dto::TeamSpaceSettings;
+    use syno_api::foto::setting::user::dto::UserSettings;
+
+    let renamed = Arc::new(Mutex::new(vec![]));
+    let renamed_albums = renamed.clone();
+    let client = FakeHttpClient::new(move |request| {
+        if request.is(foto::setting::user::API, "get") {
+            return FakeResponse::data(UserSettings::default());
+        }
+        if request.is(foto::setting::team_space::API, "get") {
+            return FakeResponse::data(TeamSpaceSettings::default());
+        }
+        if request.is(foto::browse::album::API, "set_name") {
+            renamed_albums
+                .lock()
+                .unwrap()
+                .push((request.param("id").unwrap(), request.param("name").unwrap()));
+            return FakeResponse::data(());
+        }
+        let albums: Vec<_> = ["trip", "Holidays"]
+            .into_iter()
+            .enumerate()
+            .filter(|_| request.param("offset").unwrap() == "0")
+            .filter(|_| request.is(foto::browse::album::API, "list"))
+            .map(|(id, name)| AlbumDto {
+                id: id as u32 + 1,
+                name: name.to_string(),
+                ..AlbumDto::default()
+            })
+            .collect();
+        FakeResponse::data(serde_json::json!({ "list": albums }))
+    });
+    let conf = logged_in_conf();
+    let fs = FakeFs::new();
+    let mut io = FakeIo::new("");
+    let rename = |album_name: &str, new_name: &str| AlbumCommand::Rename {
+        album_name: album_name.to_string(),
+        new_name: new_name.to_string(),
+    };
+
+    handle(rename("trip", "Trip"), &conf, &client, &mut io, &fs)
+        .await
+        .unwrap();
+    let error = handle(rename("trip", "HOLIDAYS"), &conf, &client, &mut io, &fs)
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.to_string(), "album 'Holidays' already exists");
+    assert_eq!(
+        *renamed.lock().unwrap(),
+        [("1".to_string(), r#""Trip""#.to_string())]
+    );
+    assert_eq!(io.stdout_str(), "Renamed album 'trip' to 'Trip'\n");
+}
@@ -1,14 +1,19 @@
 use super::{Album, DsmError};
-use crate::commands::error::HttpError;
+use crate::commands::dto::Place;
+use crate::commands::error::{HttpError, is_session_error};
 use crate::conf::Session;
 use crate::http::{HttpClient, HttpResponse, Url};
 use anyhow::{Result, bail};
+use clap::ValueEnum;
+use derive_more::Display;
+use futures::stream::{self, Stream, TryStreamExt};
 use reqwest::IntoUrl;
 use serde::Deserialize;
 use serde::de::DeserializeOwned;
 use std::future::Future;
 use syno_api::dto::{ApiResponse, List};
 use syno_api::foto::browse::album::dto::Album as AlbumDto;
+use syno_api::foto::browse::folder::dto::Folder;
 use syno_api::foto::browse::item::dto::Item;
 use syno_api::foto::browse::person::dto::Person;
 use syno_api::foto::search::dto::Search;
@@ -43,33 +48,22 @@ impl<C: HttpClient> ApiClient for C {
     async fn get<U, R>(
         &self,
         url: U,
-        ApiParams {
-            api,
-            method,
-            version,
-        }: ApiParams<'_>,
+        api_params: ApiParams<'_>,
         params: &[(&str, &str)],
     ) -> Result<R>
     where
         U: IntoUrl,
         R: DeserializeOwned + 'static,
     {
-        let mut url = url.into_url()?;
-        let path = url.path().trim_end_matches('/');
-        url.set_path(format!("{path}/webapi/entry.cgi").as_str());
-        let mut query = format!(
-            "api={}&\
-            method={}&\
-            version={}",
-            api, method, version
-        );
-        for (key, value) in params {
-            query.push_str(format!("&{key}={value}").as_str());
+        let url = entry_url(url, api_params, params)?;
+        let response = C::get(self, url.clone()).await?;
+        match try_deserialize_response_content(response).await {
+            Err(error) if is_session_error(&error) && self.relogin().await? => {
+                let response = C::get(self, url).await?;
+                try_deserialize_response_content(response).await
+            }
+            result => result,
         }
-        url.set_query(query.as_str().into());
-        let response = C::get(self, url).await?;
-        let data_dto = try_deserialize_response_content(response).await?;
-        Ok(data_dto)
     }
 
     async fn post<U, R>(
@@ -95,10 +89,41 @@ impl<C: HttpClient> ApiClient for C {
         for param in params {
             form.push(*param);
         }
-        let response = C::post(self, url, &form).await?;
-        let data_dto = try_deserialize_response_content(response).await?;
-        Ok(data_dto)
+        let response = C::post(self, url.clone(), &form).await?;
+        match try_deserialize_response_content(response).await {
+            Err(error) if is_session_error(&error) && self.relogin().await? => {
+                let response = C::post(self, url, &form).await?;
+                try_deserialize_response_content(response).await
+            }
+            result => result,
+        }
+    }
+}
+
+/// Build a GET request URL of `webapi/entry.cgi` endpoint
+pub fn entry_url<U: IntoUrl>(
+    url: U,
+    ApiParams {
+        api,
+        method,
+        version,
+    }: ApiParams<'_>,
+    params: &[(&str, &str)],
+) -> Result<Url> {
+    let mut url = url.into_url()?;
+    let path = url.path().trim_end_matches('/');
+    url.set_path(format!("{path}/webapi/entry.cgi").as_str());
+    let mut query = format!(
+        "api={}&\
+        method={}&\
+        version={}",
+        api, method, version
+    );
+    for (key, value) in params {
+        query.push_str(format!("&{key}={value}").as_str());
     }
+    url.set_query(query.as_str().into());
+    Ok(url)
 }
 
 async fn try_deserialize_response_content<R, D>(response: R) -> Result<D>
@@ -121,9 +146,12 @@ where
         bail!(DsmError::from(code));
     }
     assert!(dto.success);
-    Ok(dto
-        .data
-        .expect("data should be populated on successful response"))
+    match dto.data {
+        Some(data) => Ok(data),
+        /* Some methods (e.g. delete) respond without data, which is only expected when `D` is
+         * e.g. `IgnoredAny` */
+        None => Ok(serde_json::from_value(serde_json::Value::Null)?),
+    }
 }
 
 /// Request-parameters required by Synology Photos API
@@ -144,6 +172,16 @@ impl<'a> ApiParams<'a> {
     }
 }
 
+/// Default number of entries requested at once by [SessionClient::pages]
+pub const PAGE_SIZE: u32 = 500;
+
+/// Range of entries requested from a "list" API method
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub offset: u32,
+    pub limit: u32,
+}
+
 /// Provides methods to query Synology Photos API when logged-in. Used by multiple commands.
 pub struct SessionClient<'a, C> {
     pub(crate) dsm_url: &'a Url,
@@ -168,98 +206,99 @@ impl<'a, C: ApiClient> SessionClient<'a, C> {
             .await
     }
 
-    pub async fn get_team_space_settings(&self) -> Result<TeamSpaceSettings> {
-        self.client
+    /// Name of the signed-in user
+    pub async fn get_user_name(&self) -> Result<String> {
+        #[derive(Debug, Deserialize)]
+        struct Me {
+            name: String,
+        }
+
+        let me: Me = self
+            .client
             .get(
                 self.dsm_url.clone(),
-                ApiParams::new(foto::setting::team_space::API, "get", 1),
+                ApiParams::new(foto::user_info::API, "me", 1),
                 &[],
             )
-            .await
+            .await?;
+        Ok(me.name)
     }
 
-    pub async fn count_owned_albums(&self) -> Result<u32> {
-        #[derive(Debug, Deserialize)]
-        struct CountContainer {
-            count: u32,
-        }
-
-        let data: CountContainer = self
-            .client
+    pub async fn get_team_space_settings(&self) -> Result<TeamSpaceSettings> {
+        self.client
             .get(
                 self.dsm_url.clone(),
-                ApiParams::new(foto::browse::album::API, "count", 2),
+                ApiParams::new(foto::setting::team_space::API, "get", 1),
                 &[],
             )
-            .await?;
-        Ok(data.count)
+            .await
     }
 
-    pub async fn list_owned_albums(&self, limit: u32) -> Result<Vec<AlbumDto>> {
+    pub async fn list_owned_albums(&self, page: Page) -> Result<Vec<AlbumDto>> {
         let data: List<AlbumDto> = self
             .client
             .get(
                 self.dsm_url.clone(),
                 ApiParams::new(foto::browse::album::API, "list", 2),
-                &[("offset", "0"), ("limit", limit.to_string().as_str())],
+                &[
+                    ("offset", page.offset.to_string().as_str()),
+                    ("limit", page.limit.to_string().as_str()),
+                ],
             )
             .await?;
         Ok(data.list)
     }
 
-    pub async fn list_shared_with_me_albums(
-        &self,
-        offset: u32,
-        limit: u32,
-    ) -> Result<Vec<AlbumDto>> {
+    pub async fn list_shared_with_me_albums(&self, page: Page) -> Result<Vec<AlbumDto>> {
         let data: List<AlbumDto> = self
             .client
             .get(
                 self.dsm_url.clone(),
                 ApiParams::new(foto::sharing::misc::API, "list_shared_with_me_album", 2),
                 &[
-                    ("offset", offset.to_string().as_str()),
-                    ("limit", limit.to_string().as_str()),
+                    ("offset", page.offset.to_string().as_str()),
+                    ("limit", page.limit.to_string().as_str()),
                 ],
             )
             .await?;
         Ok(data.list)
     }
 
-    pub async fn count_people(&self, space: Space) -> Result<u32> {
-        #[derive(Debug, Deserialize)]
-        struct CountContainer {
-            count: u32,
-        }
-
-        let data: CountContainer = self
+    pub async fn list_people(&self, space: Space, page: Page) -> Result<Vec<Person>> {
+        let data: List<Person> = self
             .client
             .get(
                 self.dsm_url.clone(),
-                ApiParams::new(space.browse_person_api(), "count", 2),
-                &[("show_more", true.to_string().as_str())],
+                ApiParams::new(space.browse_person_api(), "list", 1),
+                &[
+                    ("offset", page.offset.to_string().as_str()),
+                    ("limit", page.limit.to_string().as_str()),
+                ],
             )
             .await?;
-        Ok(data.count)
+        Ok(data.list)
     }
 
-    pub async fn list_people(&self, space: Space, limit: u32) -> Result<Vec<Person>> {
-        let data: List<Person> = self
+    pub async fn list_places(&self, space: Space, page: Page) -> Result<Vec<Place>> {
+        let data: List<Place> = self
             .client
             .get(
                 self.dsm_url.clone(),
-                ApiParams::new(space.browse_person_api(), "list", 1),
-                &[("offset", "0"), ("limit", limit.to_string().as_str())],
+                ApiParams::new(space.browse_geocoding_api(), "list", 1),
+                &[
+                    ("offset", page.offset.to_string().as_str()),
+                    ("limit", page.limit.to_string().as_str()),
+                ],
             )
             .await?;
         Ok(data.list)
     }
 
-    pub async fn list_items(&self, album: &Album, limit: u32) -> Result<Vec<Item>> {
+    pub async fn list_items(&self, album: &Album, page: Page) -> Result<Vec<Item>> {
         let (key, value) = album.id_param();
         let api = match album {
             Album::Normal(_) => foto::browse::item::API,
-            Album::Person(_, space) => space.browse_item_api(),
+            Album::Person(_, space) | Album::Place(_, space) => space.browse_item_api(),
         };
         let items: List<Item> = self
             .client
@@ -268,14 +307,134 @@ impl<'a, C: ApiClient> SessionClient<'a, C> {
                 ApiParams::new(api, "list", 1),
                 &[
                     (key, value.as_str()),
-                    ("offset", "0"),
-                    ("limit", limit.to_string().as_str()),
+                    ("offset", page.offset.to_string().as_str()),
+                    ("limit", page.limit.to_string().as_str()),
+                    ("additional", r#"["resolution"]"#),
+                ],
+            )
+            .await?;
+        Ok(items.list)
+    }
+
+    /// * `name` - folder path, starting with `/`
+    pub async fn get_folder_by_name(&self, (name, space): (&str, Space)) -> Result<Folder> {
+        #[derive(Debug, Deserialize)]
+        struct FolderContainer {
+            folder: Folder,
+        }
+
+        let folder: FolderContainer = self
+            .client
+            .get(
+                self.dsm_url.clone(),
+                ApiParams::new(space.browse_folder_api(), "get", 1),
+                &[("name", name)],
+            )
+            .await?;
+        Ok(folder.folder)
+    }
+
+    /// Direct subfolders of the folder
+    pub async fn list_subfolders(
+        &self,
+        (folder_id, space): (u32, Space),
+        page: Page,
+    ) -> Result<Vec<Folder>> {
+        let folders: List<Folder> = self
+            .client
+            .get(
+                self.dsm_url.clone(),
+                ApiParams::new(space.browse_folder_api(), "list", 1),
+                &[
+                    ("id", folder_id.to_string().as_str()),
+                    ("offset", page.offset.to_string().as_str()),
+                    ("limit", page.limit.to_string().as_str()),
+                ],
+            )
+            .await?;
+        Ok(folders.list)
+    }
+
+    /// Items directly in the folder, not including subfolders
+    pub async fn list_folder_items(
+        &self,
+        (folder_id, space): (u32, Space),
+        page: Page,
+    ) -> Result<Vec<Item>> {
+        let items: List<Item> = self
+            .client
+            .get(
+                self.dsm_url.clone(),
+                ApiParams::new(space.browse_item_api(), "list", 1),
+                &[
+                    ("folder_id", folder_id.to_string().as_str()),
+                    ("offset", page.offset.to_string().as_str()),
+                    ("limit", page.limit.to_string().as_str()),
                 ],
             )
             .await?;
         Ok(items.list)
     }
 
+    /// Items in the whole timeline of the space
+    pub async fn list_space_items(&self, space: Space, page: Page) -> Result<Vec<Item>> {
+        let items: List<Item> = self
+            .client
+            .get(
+                self.dsm_url.clone(),
+                ApiParams::new(space.browse_item_api(), "list", 1),
+                &[
+                    ("offset", page.offset.to_string().as_str()),
+                    ("limit", page.limit.to_string().as_str()),
+                ],
+            )
+            .await?;
+        Ok(items.list)
+    }
+
+    /// All items of `album`, fetched in pages
+    pub async fn list_all_items(&self, album: &Album) -> Result<Vec<Item>> {
+        self.all_pages(PAGE_SIZE, |client, page| client.list_items(album, page))
+            .await
+    }
+
+    /// Stream of pages of up to `page_size` entries returned by `fetch`. Ends with the first empty
+    /// page, so that the number of entries does not need to be known upfront. DSM may return
+    /// fewer entries than requested, so a short page does not mean it is the last one.
+    pub fn pages<'s, T, F, Fut>(
+        &'s self,
+        page_size: u32,
+        fetch: F,
+    ) -> impl Stream<Item = Result<Vec<T>>> + 's
+    where
+        T: 's,
+        F: FnMut(&'s Self, Page) -> Fut + 's,
+        Fut: Future<Output = Result<Vec<T>>> + 's,
+    {
+        stream::try_unfold((fetch, 0), move |(mut fetch, offset)| async move {
+            let page = Page {
+                offset,
+                limit: page_size,
+            };
+            let entries = fetch(self, page).await?;
+            if entries.is_empty() {
+                return Ok(None);
+            }
+            let next_offset = offset + entries.len() as u32;
+            Ok(Some((entries, (fetch, next_offset))))
+        })
+    }
+
+    /// Entries of all [Self::pages] in a single `Vec`
+    pub async fn all_pages<'s, T, F, Fut>(&'s self, page_size: u32, fetch: F) -> Result<Vec<T>>
+    where
+        T: 's,
+        F: FnMut(&'s Self, Page) -> Fut + 's,
+        Fut: Future<Output = Result<Vec<T>>> + 's,
+    {
+        self.pages(page_size, fetch).try_concat().await
+    }
+
     /// This is unreliable on the API side (returns errors e.g. when keyword starts with numbers,
     /// or just doesn't return anything in other scenarios...). Use only for informational purposes.
     pub async fn suggest_albums(&self, album_name: &str) -> Result<Vec<Search>> {
@@ -287,8 +446,7 @@ impl<'a, C: ApiClient> SessionClient<'a, C> {
                 &[("keyword", album_name)],
             )
             .await?;
-        // TODO add support for "places" albums
-        const SUPPORTED_ALBUM_TYPES: [&str; 3] = ["album", "person", "shared_with_me"];
+        const SUPPORTED_ALBUM_TYPES: [&str; 4] = ["album", "person", "shared_with_me", "geocoding"];
         let albums = data
             .list
             .into_iter()
@@ -298,9 +456,15 @@ impl<'a, C: ApiClient> SessionClient<'a, C> {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Display, Copy, Clone, PartialEq, Eq, Hash, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum Space {
+    /// The user's Personal Space
+    #[default]
+    #[display("Personal Space")]
     Personal,
+    /// Shared Space (team space)
+    #[display("Shared Space")]
     Shared,
 }
 
@@ -312,10 +476,53 @@ impl Space {
         }
     }
 
+    pub fn browse_folder_api(&self) -> &'static str {
+        match self {
+            Space::Personal => foto::browse::folder::API,
+            Space::Shared => foto_team::browse::folder::API,
+        }
+    }
+
     pub fn browse_item_api(&self) -> &'static str {
         match self {
             Space::Personal => foto::browse::item::API,
             Space::Shared => foto_team::browse::item::API,
         }
     }
+
+    pub fn browse_geocoding_api(&self) -> &'static str {
+        match self {
+            Space::Personal => "SYNO.Foto.Browse.Geocoding",
+            Space::Shared => "SYNO.FotoTeam.Browse.Geocoding",
+        }
+    }
+}
+
+#[tokio::test]
+async fn all_pages_continues_after_short_pages() {
+    use crate::test::{FakeHttpClient, FakeResponse, logged_in_conf};
+
+    /* DSM returning at most 200 of 500 requested entries */
+    let client = FakeHttpClient::new(|request| {
+        assert!(request.is(foto::browse::album::API, "list"));
+        let offset: u32 = request.param("offset").unwrap().parse().unwrap();
+        let limit: u32 = request.param("limit").unwrap().parse().unwrap();
+        let albums: Vec<_> = (offset..(offset + limit.min(200)).min(450))
+            .map(|id| AlbumDto {
+                id,
+                ..AlbumDto::default()
+            })
+            .collect();
+        FakeResponse::data(serde_json::json!({ "list": albums }))
+    });
+    let conf = logged_in_conf();
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+
+    let albums = client
+        .all_pages(PAGE_SIZE, |c, page| c.list_owned_albums(page))
+        .await
+        .unwrap();
+
+    let ids: Vec<u32> = albums.iter().map(|a| a.id).collect();
+    assert_eq!(ids, (0..450).collect::<Vec<_>>());
 }
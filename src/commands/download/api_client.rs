@@ -0,0 +1,75 @@
+//! Extra methods for SessionClient used by download command
+
+use crate::commands::DsmError;
+use crate::commands::api_client::{ApiParams, SessionClient, Space, entry_url};
+use crate::commands::error::{HttpError, is_session_error};
+use crate::http::{HttpClient, HttpResponse, Url};
+use anyhow::{Result, bail};
+use serde::de::IgnoredAny;
+use syno_api::dto::ApiResponse;
+
+/// Content of a downloaded file. The first chunk is read upfront, since DSM responds with an API
+/// error (e.g. when the session has expired) instead of the file content.
+pub struct Download<R> {
+    first_chunk: Option<Vec<u8>>,
+    response: R,
+}
+
+impl<R: HttpResponse> Download<R> {
+    /// Next chunk of file content, `None` when the whole file has been read
+    pub async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        match self.first_chunk.take() {
+            Some(chunk) => Ok(Some(chunk)),
+            None => self.response.chunk().await,
+        }
+    }
+}
+
+impl<C: HttpClient> SessionClient<'_, C> {
+    /// Request original file of an item. Returned file content can be read in chunks. The request
+    /// is retried once when the session has expired and the client has signed in again.
+    pub async fn download_item(&self, item_id: u32, space: Space) -> Result<Download<C::Response>> {
+        let api = match space {
+            Space::Personal => "SYNO.Foto.Download",
+            Space::Shared => "SYNO.FotoTeam.Download",
+        };
+        let url = entry_url(
+            self.dsm_url.clone(),
+            ApiParams::new(api, "download", 2),
+            &[
+                ("item_id", format!("[{item_id}]").as_str()),
+                ("download_type", "source"),
+                ("force_download", "true"),
+            ],
+        )?;
+        match self.start_download(url.clone()).await {
+            Err(error) if is_session_error(&error) && self.client.relogin().await? => {
+                self.start_download(url).await
+            }
+            result => result,
+        }
+    }
+
+    async fn start_download(&self, url: Url) -> Result<Download<C::Response>> {
+        let mut response = HttpClient::get(self.client, url).await?;
+        if !response.status().is_success() {
+            bail!(HttpError(response.status()));
+        }
+        let first_chunk = response.chunk().await?;
+        /* Files never start with a JSON object, while API errors are short enough to come in a
+         * single chunk */
+        if let Some(chunk) = first_chunk.as_deref().filter(|c| c.starts_with(b"{")) {
+            if let Ok(ApiResponse::<IgnoredAny> {
+                error: Some(syno_api::dto::Error { code }),
+                ..
+            }) = serde_json::from_slice(chunk)
+            {
+                bail!(DsmError::from(code));
+            }
+        }
+        Ok(Download {
+            first_chunk,
+            response,
+        })
+    }
+}
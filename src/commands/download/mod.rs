@@ -0,0 +1,262 @@
+use crate::commands::api_client::{SessionClient, Space};
+use crate::commands::{Album, album_not_found, find_album};
+use crate::conf::Conf;
+use crate::fs::Fs;
+use crate::http::{HttpClient, HttpResponse};
+use crate::io::Io;
+use anyhow::{Result, anyhow, bail};
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+use syno_api::foto::browse::item::dto::Item;
+use syno_api::foto::setting::user::dto::UserSettings;
+
+mod api_client;
+
+use api_client::Download;
+
+/// * `target_dir` - local directory, created if it does not exist
+pub async fn handle<C: HttpClient, I: Io, F: Fs>(
+    album_name: &str,
+    target_dir: &Path,
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+    fs: &F,
+) -> Result<()> {
+    if !conf.is_logged_in() {
+        bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
+    }
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
+    let user_settings = client.get_user_settings().await?;
+    let team_space_settings = client.get_team_space_settings().await?;
+
+    match find_album(album_name, &user_settings, &team_space_settings, &client).await? {
+        Some(album) => download((album, target_dir, user_settings), &client, io, fs).await,
+        None => {
+            let matching_albums = client.suggest_albums(album_name).await.unwrap_or_else(|e| {
+                log::warn!("suggest album search error: {e}");
+                vec![]
+            });
+            album_not_found(album_name, matching_albums, io)
+        }
+    }
+}
+
+enum Outcome {
+    Downloaded,
+    Skipped,
+}
+
+async fn download<C: HttpClient, I: Io, F: Fs>(
+    (album, target_dir, user_settings): (Album, &Path, UserSettings),
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+    fs: &F,
+) -> Result<()> {
+    let photos = client.list_all_items(&album).await?;
+    fs.create_dir_all(target_dir)?;
+    writeln!(
+        io.stdout(),
+        "Downloading {} items from album '{}' to '{}'",
+        photos.len(),
+        album.name(),
+        target_dir.display()
+    )?;
+
+    let shared_space_access = user_settings.team_space_permission != "none";
+    let (photos, inaccessible_photos): (Vec<_>, Vec<_>) = photos
+        .into_iter()
+        .partition(|p| p.owner_user_id != 0 || shared_space_access);
+    if !inaccessible_photos.is_empty() {
+        log::warn!(
+            "album contains items from Shared Space, but you don't have access to it; \
+             skipping {} item(s)",
+            inaccessible_photos.len()
+        );
+    }
+
+    const CONCURRENT_DOWNLOADS: usize = 4;
+    let file_names = target_file_names(&photos);
+    let results: Vec<_> = stream::iter(photos.into_iter().zip(file_names))
+        .map(|(photo, file_name)| async move {
+            let result = match file_name {
+                Some(file_name) => {
+                    download_item((&photo, &file_name), target_dir, client, fs).await
+                }
+                None => Err(anyhow!("invalid file name")),
+            };
+            (photo, result)
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS)
+        .collect()
+        .await;
+
+    let (mut downloaded, mut skipped, mut failed) = (0, 0, 0);
+    for (Item { filename, .. }, result) in results {
+        match result {
+            Ok(Outcome::Downloaded) => downloaded += 1,
+            Ok(Outcome::Skipped) => skipped += 1,
+            Err(e) => {
+                failed += 1;
+                writeln!(io.stdout(), "Error: {e} '{filename}'")?;
+            }
+        }
+    }
+    writeln!(
+        io.stdout(),
+        "Download summary: {downloaded} downloaded, {skipped} skipped, {failed} failed"
+    )?;
+    Ok(())
+}
+
+/// Names of downloaded files, resolved before any download starts. Items sharing a file name (e.g.
+/// from different folders) get their id appended, e.g. `IMG_0001_1234.jpg`, so that concurrent
+/// downloads never write the same file. `None` when item's file name is invalid.
+fn target_file_names(photos: &[Item]) -> Vec<Option<OsString>> {
+    let file_names: Vec<_> = photos
+        .iter()
+        .map(|p| Path::new(p.filename.as_str()).file_name())
+        .collect();
+    let mut counts: HashMap<&OsStr, usize> = HashMap::new();
+    for file_name in file_names.iter().flatten() {
+        *counts.entry(file_name).or_default() += 1;
+    }
+    photos
+        .iter()
+        .zip(&file_names)
+        .map(|(photo, file_name)| {
+            let file_name = (*file_name)?;
+            if counts[file_name] == 1 {
+                return Some(file_name.to_os_string());
+            }
+            let path = Path::new(file_name);
+            let mut unique_name = path.file_stem()?.to_os_string();
+            unique_name.push(format!("_{}", photo.id));
+            if let Some(extension) = path.extension() {
+                unique_name.push(".");
+                unique_name.push(extension);
+            }
+            Some(unique_name)
+        })
+        .collect()
+}
+
+/// Stream `photo` file into `target_dir` as `file_name`. The file is first written to a temporary
+/// `.part` file which gets renamed when download is complete and its size verified.
+async fn download_item<C: HttpClient, F: Fs>(
+    (photo, file_name): (&Item, &OsStr),
+    target_dir: &Path,
+    client: &SessionClient<'_, C>,
+    fs: &F,
+) -> Result<Outcome> {
+    let path = target_dir.join(file_name);
+    if let Ok(metadata) = fs.metadata(&path) {
+        if metadata.len() == u64::from(photo.filesize) {
+            return Ok(Outcome::Skipped);
+        }
+        bail!("a different file with the same name already exists");
+    }
+
+    let space = match photo.owner_user_id {
+        0 => Space::Shared,
+        _ => Space::Personal,
+    };
+    let mut response = client.download_item(photo.id, space).await?;
+    let mut part_file_name = file_name.to_os_string();
+    part_file_name.push(".part");
+    let part_path = target_dir.join(part_file_name);
+    let size = match write_body(&mut response, &part_path, fs).await {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = fs.remove_file(&part_path);
+            return Err(e);
+        }
+    };
+    if size != u64::from(photo.filesize) {
+        fs.remove_file(&part_path)?;
+        bail!(
+            "size mismatch, expected {} bytes but received {size}",
+            photo.filesize
+        );
+    }
+    fs.rename(&part_path, &path)?;
+    fs.set_modified(&path, UNIX_EPOCH + Duration::from_secs(photo.time))?;
+    Ok(Outcome::Downloaded)
+}
+
+/// Returns number of bytes written
+async fn write_body<R: HttpResponse, F: Fs>(
+    response: &mut Download<R>,
+    path: &Path,
+    fs: &F,
+) -> Result<u64> {
+    let mut file = fs.create(path)?;
+    let mut size = 0;
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+        size += chunk.len() as u64;
+    }
+    file.flush()?;
+    Ok(size)
+}
+
+#[tokio::test]
+async fn download_items_with_the_same_name_to_separate_files() {
+    use crate::test::{FakeFs, FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use syno_api::foto::browse::album::dto::Album as AlbumDto;
+
+    let photos = [(11, "IMG_1.jpg", "first"), (12, "IMG_1.jpg", "second")];
+    let client = FakeHttpClient::new(move |request| {
+        if request.is("SYNO.Foto.Browse.Item", "list") {
+            let offset = request.param("offset").unwrap();
+            let items: Vec<_> = photos
+                .iter()
+                .filter(|_| offset == "0")
+                .map(|&(id, filename, content)| Item {
+                    id,
+                    filename: filename.to_string(),
+                    filesize: content.len() as u32,
+                    owner_user_id: 1,
+                    ..Item::default()
+                })
+                .collect();
+            return FakeResponse::data(serde_json::json!({ "list": items }));
+        }
+        assert!(request.is("SYNO.Foto.Download", "download"));
+        let item_id = request.param("item_id").unwrap();
+        let &(_, _, content) = photos
+            .iter()
+            .find(|(id, _, _)| item_id == format!("[{id}]"))
+            .unwrap();
+        FakeResponse::body(content)
+    });
+    let conf = logged_in_conf();
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+    let (mut io, fs) = (FakeIo::new(""), FakeFs::new());
+    let album = Album::Normal(AlbumDto {
+        id: 1,
+        name: "Holidays".to_string(),
+        ..AlbumDto::default()
+    });
+
+    download(
+        (album, Path::new("/out"), UserSettings::default()),
+        &client,
+        &mut io,
+        &fs,
+    )
+    .await
+    .unwrap();
+
+    assert!(
+        io.stdout_str()
+            .contains("2 downloaded, 0 skipped, 0 failed")
+    );
+    assert_eq!(fs.read_to_string("/out/IMG_1_11.jpg").unwrap(), "first");
+    assert_eq!(fs.read_to_string("/out/IMG_1_12.jpg").unwrap(), "second");
+    assert!(!fs.exists(Path::new("/out/IMG_1.jpg")));
+}
@@ -0,0 +1,11 @@
+//! DTOs for Synology Photos API responses not (yet) provided by `syno_api` crate
+
+use serde::{Deserialize, Serialize};
+
+/// Entry of "Places" auto-album (SYNO.Foto.Browse.Geocoding)
+#[derive(Debug, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct Place {
+    pub id: u32,
+    pub name: String,
+    pub item_count: u32,
+}
@@ -0,0 +1,338 @@
+//! Detection of photos copied to multiple locations, e.g. to both Personal and Shared Space
+
+use crate::commands::api_client::{SessionClient, Space};
+use crate::commands::list::locate;
+use crate::commands::list::path_mapping::PathMapper;
+use crate::commands::list_items::format_time;
+use crate::commands::{find_album, list_folder_tree};
+use crate::conf::Conf;
+use crate::http::HttpClient;
+use crate::io::Io;
+use crate::output::{OutputFormat, Record, write_records};
+use anyhow::{Result, bail};
+use futures::stream::{self, StreamExt};
+use ring::digest::{Context, SHA256};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use syno_api::foto::browse::item::dto::Item;
+
+/// `(albums, folders, shared_folders, recursive)` - albums, Personal Space and Shared Space
+/// folders to scan, and whether to include subfolders
+pub type SourceArgs = (Vec<String>, Vec<String>, Vec<String>, bool);
+
+/// Items which are copies of each other, with their content hashes when computed
+type Group = Vec<(Item, Option<String>)>;
+
+/// Item which has copies
+#[derive(Debug, Serialize)]
+struct DuplicateRecord {
+    /// Items with the same group number are copies of each other
+    group: usize,
+    item_id: u32,
+    filename: String,
+    filesize: u32,
+    taken: String,
+    /// SHA-256 of file content (hex), only computed with `--hash`
+    hash: Option<String>,
+    /// Resolved file-system path on NAS
+    path: Option<String>,
+    space: &'static str,
+    error_message: Option<String>,
+}
+
+impl Record for DuplicateRecord {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "group",
+            "item_id",
+            "filename",
+            "filesize",
+            "taken",
+            "hash",
+            "path",
+            "space",
+            "error_message",
+        ]
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.group.to_string(),
+            self.item_id.to_string(),
+            self.filename.clone(),
+            self.filesize.to_string(),
+            self.taken.clone(),
+            self.hash.clone().unwrap_or_default(),
+            self.path.clone().unwrap_or_default(),
+            self.space.to_string(),
+            self.error_message.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// * `hash` - group by content hash instead of file name and time taken
+pub async fn handle<C: HttpClient, I: Io>(
+    (albums, folders, shared_folders, recursive): SourceArgs,
+    hash: bool,
+    output: OutputFormat,
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+) -> Result<()> {
+    if !conf.is_logged_in() {
+        bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
+    }
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
+    let user_settings = client.get_user_settings().await?;
+    let team_space_settings = client.get_team_space_settings().await?;
+
+    /* The same item can be found in multiple albums or folders */
+    let mut items = BTreeMap::new();
+    for album_name in albums {
+        let Some(album) =
+            find_album(&album_name, &user_settings, &team_space_settings, &client).await?
+        else {
+            bail!("album '{album_name}' not found")
+        };
+        items.extend(
+            client
+                .list_all_items(&album)
+                .await?
+                .into_iter()
+                .map(|i| (i.id, i)),
+        );
+    }
+    let folders = folders
+        .into_iter()
+        .map(|f| (f, Space::Personal))
+        .chain(shared_folders.into_iter().map(|f| (f, Space::Shared)));
+    for (folder_path, space) in folders {
+        let folder_items =
+            list_folder_tree((folder_path.as_str(), space), recursive, &client).await?;
+        items.extend(folder_items.into_iter().map(|i| (i.id, i)));
+    }
+
+    let groups = if hash {
+        let shared_space_access = user_settings.team_space_permission != "none";
+        group_by_hash(
+            items.into_values().collect(),
+            shared_space_access,
+            &client,
+            io,
+        )
+        .await?
+    } else {
+        let mut groups: HashMap<(String, u32, u64), Vec<Item>> = HashMap::new();
+        for item in items.into_values() {
+            groups
+                .entry((item.filename.clone(), item.filesize, item.time))
+                .or_default()
+                .push(item);
+        }
+        groups
+            .into_values()
+            .map(|items| items.into_iter().map(|i| (i, None)).collect())
+            .collect()
+    };
+    let mut groups: Vec<Group> = groups.into_iter().filter(|g| g.len() > 1).collect();
+    groups.sort_by(|a, b| a[0].0.filename.cmp(&b[0].0.filename));
+
+    let group_count = groups.len();
+    let mut details = HashMap::new();
+    let mut duplicates = vec![];
+    for (group, items) in groups.into_iter().enumerate() {
+        for (item, hash) in items {
+            details.insert(item.id, (group + 1, item.filesize, item.time, hash));
+            duplicates.push(item);
+        }
+    }
+    let path_mapper = PathMapper::new(
+        conf.path_mappings.clone(),
+        (conf.volume_paths, conf.homes_path.clone()),
+    );
+    let mut records: Vec<DuplicateRecord> =
+        locate(duplicates, (&user_settings, &path_mapper), &client)
+            .await?
+            .into_iter()
+            .map(|location| {
+                let (group, filesize, time, hash) = details[&location.item_id].clone();
+                DuplicateRecord {
+                    group,
+                    item_id: location.item_id,
+                    filename: location.filename,
+                    filesize,
+                    taken: format_time(time),
+                    hash,
+                    path: location.path,
+                    space: location.space,
+                    error_message: location.error_message,
+                }
+            })
+            .collect();
+    records.sort_by_key(|r| (r.group, r.item_id));
+    if output != OutputFormat::Text {
+        return write_records(&records, output, io.stdout());
+    }
+    print_groups(records, group_count, io)
+}
+
+/// Download items which have the same size as other items, and group them by content hash.
+/// Shared Space items are skipped when the user has no access to Shared Space.
+async fn group_by_hash<C: HttpClient, I: Io>(
+    items: Vec<Item>,
+    shared_space_access: bool,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+) -> Result<Vec<Group>> {
+    let mut by_size: HashMap<u32, Vec<Item>> = HashMap::new();
+    for item in items {
+        if item.owner_user_id == 0 && !shared_space_access {
+            continue;
+        }
+        by_size.entry(item.filesize).or_default().push(item);
+    }
+    let candidates: Vec<Item> = by_size
+        .into_values()
+        .filter(|items| items.len() > 1)
+        .flatten()
+        .collect();
+    /* Progress goes to stderr, so that machine-readable output is not affected */
+    writeln!(
+        io.stderr(),
+        "Computing content hashes of {} items",
+        candidates.len()
+    )?;
+
+    const CONCURRENT_DOWNLOADS: usize = 4;
+    let results: Vec<_> = stream::iter(candidates)
+        .map(|item| async move {
+            let result = content_hash(&item, client).await;
+            (item, result)
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS)
+        .collect()
+        .await;
+    let mut groups: HashMap<(u32, String), Group> = HashMap::new();
+    for (item, result) in results {
+        match result {
+            Ok(hash) => groups
+                .entry((item.filesize, hash.clone()))
+                .or_default()
+                .push((item, Some(hash))),
+            Err(e) => writeln!(io.stderr(), "Error: {e} '{}'", item.filename)?,
+        }
+    }
+    Ok(groups.into_values().collect())
+}
+
+/// SHA-256 of file content as hex string, computed while downloading it. Fails when the size of
+/// the downloaded content does not match, like the download command does.
+async fn content_hash<C: HttpClient>(item: &Item, client: &SessionClient<'_, C>) -> Result<String> {
+    let space = match item.owner_user_id {
+        0 => Space::Shared,
+        _ => Space::Personal,
+    };
+    let mut response = client.download_item(item.id, space).await?;
+    let mut context = Context::new(&SHA256);
+    let mut size = 0;
+    while let Some(chunk) = response.chunk().await? {
+        context.update(&chunk);
+        size += chunk.len() as u64;
+    }
+    if size != u64::from(item.filesize) {
+        bail!(
+            "size mismatch, expected {} bytes but received {size}",
+            item.filesize
+        );
+    }
+    Ok(context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+fn print_groups<I: Io>(
+    records: Vec<DuplicateRecord>,
+    group_count: usize,
+    io: &mut I,
+) -> Result<()> {
+    let mut current_group = 0;
+    for record in records {
+        if record.group != current_group {
+            current_group = record.group;
+            writeln!(
+                io.stdout(),
+                "{}{} ({} bytes, taken {}):",
+                if current_group > 1 { "\n" } else { "" },
+                record.filename,
+                record.filesize,
+                record.taken
+            )?;
+        }
+        match record {
+            DuplicateRecord {
+                path: Some(path), ..
+            } => writeln!(io.stdout(), "- {path}")?,
+            DuplicateRecord {
+                filename,
+                error_message,
+                ..
+            } => writeln!(
+                io.stdout(),
+                "- Error: {} '{filename}'",
+                error_message.unwrap_or_default()
+            )?,
+        }
+    }
+    match group_count {
+        0 => writeln!(io.stdout(), "No duplicates found")?,
+        count => writeln!(io.stdout(), "\nFound {count} groups of duplicates")?,
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn group_by_hash_compares_verified_content() {
+    use crate::test::{FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+
+    let client = FakeHttpClient::new(|request| {
+        match request.param("item_id").unwrap().as_str() {
+            "[1]" | "[2]" => FakeResponse::body("abc"),
+            "[3]" => FakeResponse::body("xyz"),
+            _ => FakeResponse::body("ab"), // incomplete download
+        }
+    });
+    let conf = logged_in_conf();
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+    let mut io = FakeIo::new("");
+    let items = (1..=4)
+        .map(|id| Item {
+            id,
+            filename: format!("{id}.jpg"),
+            filesize: 3,
+            owner_user_id: 1,
+            ..Item::default()
+        })
+        .collect();
+
+    let mut groups = group_by_hash(items, false, &client, &mut io).await.unwrap();
+
+    groups.sort_by_key(|g| g.len());
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0][0].0.id, 3);
+    let ids: Vec<u32> = groups[1].iter().map(|(item, _)| item.id).collect();
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&1) && ids.contains(&2));
+    assert_eq!(
+        groups[1][0].1.as_deref(),
+        Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+    );
+    assert!(io.stdout_str().is_empty());
+    assert!(
+        io.stderr_str()
+            .contains("Error: size mismatch, expected 3 bytes but received 2 '4.jpg'")
+    );
+}
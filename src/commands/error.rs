@@ -1,6 +1,8 @@
+use super::DsmError;
 use reqwest::StatusCode;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use syno_api::error::ApiError;
 
 #[derive(Debug)]
 pub struct HttpError(pub StatusCode);
@@ -16,3 +18,15 @@ impl Display for HttpError {
 }
 
 impl Error for HttpError {}
+
+/// Whether `error` means that DSM session has expired or is otherwise no longer valid
+pub fn is_session_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<DsmError>(),
+        Some(DsmError::Api(
+            ApiError::SessionTimeout
+                | ApiError::SessionInterruptedByDuplicatedLogin
+                | ApiError::InvalidSession
+        ))
+    )
+}
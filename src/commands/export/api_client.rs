@@ -1,9 +1,10 @@
 //! Extra methods for SessionClient used by export command
 
 use crate::commands::api_client::{ApiClient, ApiParams, SessionClient, Space};
+use crate::commands::export::OnConflict;
 use anyhow::Result;
 use serde::Deserialize;
-use syno_api::dto::List;
+use serde::de::IgnoredAny;
 use syno_api::foto::background_task::file::dto::TaskInfo;
 use syno_api::foto::{self, browse::folder::dto::Folder};
 use syno_api::foto_team;
@@ -14,24 +15,16 @@ struct FolderContainer {
 }
 
 impl<C: ApiClient> SessionClient<'_, C> {
-    pub async fn get_folder_by_name(&self, name: &str) -> Result<Folder> {
-        let folder: FolderContainer = self
-            .client
-            .get(
-                self.dsm_url.clone(),
-                ApiParams::new(foto::browse::folder::API, "get", 1),
-                &[("name", name)],
-            )
-            .await?;
-        Ok(folder.folder)
-    }
-
-    pub async fn create_folder(&self, name: &str, parent_id: u32) -> Result<Folder> {
+    pub async fn create_folder(
+        &self,
+        (name, space): (&str, Space),
+        parent_id: u32,
+    ) -> Result<Folder> {
         let folder: FolderContainer = self
             .client
             .post(
                 self.dsm_url.clone(),
-                ApiParams::new(foto::browse::folder::API, "create", 1),
+                ApiParams::new(space.browse_folder_api(), "create", 1),
                 &[
                     ("name", name),
                     ("target_id", parent_id.to_string().as_str()),
@@ -41,11 +34,13 @@ impl<C: ApiClient> SessionClient<'_, C> {
         Ok(folder.folder)
     }
 
+    /// The copy API is picked by the space of the photos; the target folder can be in either
+    /// space.
     pub async fn copy_photos(
         &self,
         photo_ids: &[u32],
         photos_space: Space,
-        target_folder_id: u32,
+        (target_folder_id, on_conflict): (u32, OnConflict),
     ) -> Result<TaskInfo> {
         #[derive(Debug, Deserialize)]
         struct TaskContainer {
@@ -69,7 +64,7 @@ impl<C: ApiClient> SessionClient<'_, C> {
                 &[
                     ("target_folder_id", target_folder_id.to_string().as_str()),
                     ("item_id", format!("[{ids}]").as_str()),
-                    ("action", "skip"),
+                    ("action", on_conflict.to_string().as_str()),
                     ("folder_id", "[]"),
                 ],
             )
@@ -77,20 +72,20 @@ impl<C: ApiClient> SessionClient<'_, C> {
         Ok(task.task_info)
     }
 
-    pub async fn get_task_status(&self, task_ids: &[u32]) -> Result<Vec<TaskInfo>> {
-        let ids = task_ids
+    pub async fn delete_items(&self, (item_ids, space): (&[u32], Space)) -> Result<()> {
+        let ids = item_ids
             .iter()
             .map(u32::to_string)
             .reduce(|acc, id| format!("{acc},{id}"))
-            .expect("task_ids should not be empty");
-        let task_infos: List<TaskInfo> = self
+            .expect("item_ids should not be empty");
+        let _: IgnoredAny = self
             .client
-            .get(
+            .post(
                 self.dsm_url.clone(),
-                ApiParams::new(foto::background_task::info::API, "get_status", 1),
+                ApiParams::new(space.browse_item_api(), "delete", 1),
                 &[("id", format!("[{ids}]").as_str())],
             )
             .await?;
-        Ok(task_infos.list)
+        Ok(())
     }
 }
@@ -0,0 +1,104 @@
+//! Ctrl-C handling for export
+//!
+//! Listening for SIGINT replaces its default action for the rest of the process, so once export
+//! starts listening, every press is handled here: a press while waiting for copy tasks interrupts
+//! every wait in progress (there are several with parallel jobs), any other one (e.g. a second
+//! press while asked whether to abort the tasks, or one between albums) exits the process.
+
+use std::future::Future;
+use std::pin::pin;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::signal;
+use tokio::sync::Notify;
+
+static LISTENER: OnceLock<Arc<Listener>> = OnceLock::new();
+
+#[derive(Default)]
+struct Listener {
+    /// Number of [interruptible] futures running
+    waiters: AtomicUsize,
+    interrupted: Notify,
+}
+
+impl Listener {
+    /// Interrupt all waiting futures; returns `false` when there are none
+    fn press(&self) -> bool {
+        if self.waiters.load(Ordering::SeqCst) == 0 {
+            return false;
+        }
+        self.interrupted.notify_waiters();
+        true
+    }
+
+    async fn interruptible<F: Future>(&self, future: F) -> Option<F::Output> {
+        /* Registered before counted as waiting, so that no press in between is missed */
+        let mut interrupted = pin!(self.interrupted.notified());
+        interrupted.as_mut().enable();
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        let output = tokio::select! {
+            output = future => Some(output),
+            _ = interrupted => None,
+        };
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+        output
+    }
+}
+
+/// Start handling Ctrl-C presses until the process exits; does nothing when already started
+pub fn listen() {
+    LISTENER.get_or_init(|| {
+        let listener = Arc::new(Listener::default());
+        let presses = listener.clone();
+        tokio::spawn(async move {
+            while signal::ctrl_c().await.is_ok() {
+                if !presses.press() {
+                    eprintln!();
+                    process::exit(130);
+                }
+            }
+        });
+        listener
+    });
+}
+
+/// Run `future` to completion (`Some`), unless Ctrl-C is pressed first (`None`). Without
+/// [listen], Ctrl-C keeps its default action.
+pub async fn interruptible<F: Future>(future: F) -> Option<F::Output> {
+    match LISTENER.get() {
+        Some(listener) => listener.interruptible(future).await,
+        None => Some(future.await),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::future;
+
+    #[tokio::test]
+    async fn press_interrupts_all_waiting_futures() {
+        let listener = Listener::default();
+        assert!(!listener.press());
+
+        let waits = async {
+            tokio::join!(
+                listener.interruptible(future::pending::<()>()),
+                listener.interruptible(future::pending::<()>())
+            )
+        };
+        let press = async {
+            while listener.waiters.load(Ordering::SeqCst) != 2 {
+                tokio::task::yield_now().await;
+            }
+            assert!(listener.press());
+        };
+        let ((first, second), ()) = tokio::join!(waits, press);
+
+        assert_eq!((first, second), (None, None));
+        assert_eq!(listener.waiters.load(Ordering::SeqCst), 0);
+        /* The press is not kept for later waits */
+        assert_eq!(listener.interruptible(future::ready(1)).await, Some(1));
+    }
+}
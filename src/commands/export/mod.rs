@@ -1,90 +1,425 @@
-use crate::commands::api_client::{ApiClient, SessionClient, Space};
+use crate::commands::api_client::{ApiClient, PAGE_SIZE, SessionClient, Space};
+use crate::commands::lookup::matches_pattern;
+use crate::commands::tasks::{is_running, wait_for_tasks};
 use crate::commands::{Album, DsmError, album_not_found, find_album};
 use crate::conf::Conf;
+use crate::fs::Fs;
 use crate::http::HttpClient;
-use crate::io::Io;
-use anyhow::{Result, bail};
+use crate::io::{Io, read_input};
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use derive_more::Display;
+use regex_lite::RegexBuilder;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::io::Write;
-use std::time::Duration;
+use std::ops::AddAssign;
+use sync::SyncState;
 use syno_api::foto::background_task::file::dto::TaskInfo;
+use syno_api::foto::browse::album::dto::Album as AlbumDto;
 use syno_api::foto::browse::item::dto::Item;
 use syno_api::foto::error::PhotoError;
+use syno_api::foto::setting::team_space::dto::TeamSpaceSettings;
 use syno_api::foto::setting::user::dto::UserSettings;
 use syno_api::foto_team::browse::folder::Folder;
-#[cfg(test)]
-use test::fake_sleep as sleep;
-#[cfg(not(test))]
-use tokio::time::sleep;
 
 mod api_client;
+mod interrupt;
+mod sync;
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ExportOptions {
+    /// Create target folder if it does not exist
+    pub create_folder: bool,
+    /// What to do with photos already present in the target folder
+    pub on_conflict: OnConflict,
+    /// Copy only items not exported by previous runs
+    pub sync: bool,
+    /// Delete items removed from the album since the previous run from the target folder
+    pub prune: bool,
+    /// Only report what would be done
+    pub dry_run: bool,
+    /// Exit once copy tasks are scheduled, without waiting for them to finish
+    pub detach: bool,
+    /// Fail when a single album is not found, instead of only reporting it
+    pub require_album: bool,
+}
 
-/// * `target_folder_path` - target folder in Personal Space (must exist)
-pub async fn handle<C: HttpClient, I: Io>(
-    album_name: &str,
-    target_folder_path: &str,
-    create_folder: bool,
+/// * `album_names` - with several names, or name patterns (see [name_matcher]), each album is
+///   exported to its own subfolder of the target folder
+/// * `target_folder_path` - target folder in `target_space` (must exist unless `create_folder` is
+///   set)
+///
+/// Returns item counts of all albums, which are zero in a dry run or when not waiting for copy
+/// tasks.
+pub async fn handle<C: HttpClient, I: Io, F: Fs>(
+    album_names: &[String],
+    (target_folder_path, target_space): (&str, Space),
+    options: ExportOptions,
     conf: &Conf,
     client: &C,
     io: &mut I,
-) -> Result<()> {
+    fs: &F,
+) -> Result<ExportSummary> {
     if !conf.is_logged_in() {
         bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
     }
     let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
+    if !options.dry_run && !options.detach {
+        /* Ctrl-C while waiting for copy tasks offers to abort them */
+        interrupt::listen();
+    }
 
     let user_settings = client.get_user_settings().await?;
-    if !user_settings.enable_home_service {
-        bail!("home service not enabled on DSM, Personal Space not available in Synology Photos")
-    }
+    let team_space_settings = client.get_team_space_settings().await?;
+    check_write_access(target_space, &user_settings, &team_space_settings)?;
 
     let folder_path = format!("/{}", target_folder_path.trim().trim_matches('/'));
-    log::info!("target folder: {folder_path}");
-    let folder_future = client.get_folder_by_name(folder_path.as_str());
+    log::info!("target folder: {folder_path} in {target_space}");
+    /* A missing folder is only created once the album is found, and never in a dry run */
+    let folder = find_folder((folder_path.as_str(), target_space), &client).await?;
+    if folder.is_none() && !options.create_folder {
+        bail!("folder '{target_folder_path}' does not exist in {target_space}")
+    }
+    let url = conf.session.as_ref().unwrap().url.as_str();
 
-    let team_space_settings = client.get_team_space_settings().await?;
-    let find_album_future = find_album(album_name, &user_settings, &team_space_settings, &client);
+    let single_album_name = match album_names {
+        [name] if !is_pattern(name) => Some(name.as_str()),
+        _ => None,
+    };
+    if let Some(album_name) = single_album_name {
+        let album = find_album(album_name, &user_settings, &team_space_settings, &client).await?;
+        let Some(album) = album else {
+            let matching_albums = client.suggest_albums(album_name).await.unwrap_or_else(|e| {
+                log::warn!("suggest album search error: {e}");
+                vec![]
+            });
+            album_not_found(album_name, matching_albums, io)?;
+            if options.require_album {
+                bail!("album '{album_name}' not found")
+            }
+            return Ok(ExportSummary::default());
+        };
+        let target = (folder, folder_path.as_str(), target_space);
+        let summary = export_album(
+            (&album, target),
+            &options,
+            (url, &user_settings),
+            &client,
+            io,
+            fs,
+        )
+        .await?;
+        return Ok(summary.unwrap_or_default());
+    }
 
-    let folder = match folder_future.await {
-        Ok(folder) => folder,
-        Err(error) => match error.downcast::<DsmError>()? {
-            DsmError::Photo(PhotoError::NoAccessOrNotFound) if create_folder => {
-                create_folder_path(folder_path.as_str(), &client).await?
+    let albums = find_albums(
+        album_names,
+        (&user_settings, &team_space_settings),
+        &client,
+        io,
+    )
+    .await?;
+    if albums.is_empty() {
+        bail!("no albums to export")
+    }
+    let subfolder_paths = subfolder_paths(&albums, &folder_path)?;
+    let mut results = vec![];
+    for (album, subfolder_path) in albums.iter().zip(subfolder_paths) {
+        let subfolder = match folder {
+            Some(_) => find_folder((subfolder_path.as_str(), target_space), &client).await?,
+            None => None,
+        };
+        let target = (subfolder, subfolder_path.as_str(), target_space);
+        let result = export_album(
+            (album, target),
+            &options,
+            (url, &user_settings),
+            &client,
+            io,
+            fs,
+        )
+        .await;
+        let interrupted = matches!(&result, Ok(Some(summary)) if summary.interrupted);
+        results.push((album.name(), result));
+        if interrupted {
+            break;
+        }
+    }
+    print_combined_summary(&results, options.dry_run, io)
+}
+
+/// Export `album` to the target folder, which is created when it does not exist (`None`), except
+/// in a dry run. Returns `None` when not waiting for copy tasks to finish.
+async fn export_album<C: ApiClient, I: Io, F: Fs>(
+    (album, (folder, folder_path, target_space)): (&Album, (Option<Folder>, &str, Space)),
+    ExportOptions {
+        on_conflict,
+        sync,
+        prune,
+        dry_run,
+        detach,
+        ..
+    }: &ExportOptions,
+    (url, user_settings): (&str, &UserSettings),
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+    fs: &F,
+) -> Result<Option<ExportSummary>> {
+    /* Personal Space folders are recorded by path only, as in versions before Shared Space export
+     * was supported */
+    let state_folder = match target_space {
+        Space::Personal => folder_path.to_string(),
+        Space::Shared => format!("shared:{folder_path}"),
+    };
+    let state_key = (url, album.name(), state_folder.as_str());
+
+    if *dry_run {
+        let target = (folder.as_ref(), folder_path, target_space);
+        let state_key = sync.then_some(state_key);
+        preview(
+            (album, target, user_settings),
+            (state_key, *on_conflict, *prune),
+            client,
+            io,
+            fs,
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    let folder = match folder {
+        Some(folder) => folder,
+        None => create_folder_path((folder_path, target_space), client).await?,
+    };
+    let target = (folder, target_space);
+    if *sync {
+        let summary = sync_export(
+            (album, target, user_settings),
+            (state_key, *on_conflict),
+            *prune,
+            client,
+            io,
+            fs,
+        )
+        .await?;
+        Ok(Some(summary))
+    } else {
+        export(
+            (album, target, user_settings),
+            (*on_conflict, *detach),
+            client,
+            io,
+        )
+        .await
+    }
+}
+
+/// Prefix of album name patterns which are regular expressions
+const REGEX_PREFIX: &str = "re:";
+/// Prefix of album name patterns with `*` and `?` wildcards; without it, those are literal
+/// characters of a name
+const GLOB_PREFIX: &str = "glob:";
+
+fn is_pattern(album_name: &str) -> bool {
+    album_name.starts_with(REGEX_PREFIX) || album_name.starts_with(GLOB_PREFIX)
+}
+
+type NameMatcher<'a> = Box<dyn Fn(&str) -> bool + 'a>;
+
+/// Case-insensitive matcher of album names: a regular expression after `re:` (matching anywhere
+/// in the name unless anchored), or a pattern with `*` and `?` wildcards after `glob:`
+fn name_matcher(pattern: &str) -> Result<NameMatcher<'_>> {
+    if let Some(glob) = pattern.strip_prefix(GLOB_PREFIX) {
+        return Ok(Box::new(move |name| matches_pattern(name, glob)));
+    }
+    let regex = pattern.strip_prefix(REGEX_PREFIX).unwrap_or(pattern);
+    let regex = RegexBuilder::new(regex)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| anyhow!("invalid regular expression '{regex}': {e}"))?;
+    Ok(Box::new(move |name| regex.is_match(name)))
+}
+
+/// Albums named by `album_names`, in the same order and without repetitions. Patterns (see
+/// [name_matcher]) match owned and shared-with-me albums, other names are looked up like in other
+/// commands. Names not matching any album are reported.
+async fn find_albums<C: ApiClient, I: Io>(
+    album_names: &[String],
+    (user_settings, team_space_settings): (&UserSettings, &TeamSpaceSettings),
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+) -> Result<Vec<Album>> {
+    /* Listed once for all names; taken out when matched */
+    let mut normal_albums: Vec<Option<AlbumDto>> = client
+        .all_pages(PAGE_SIZE, |c, page| c.list_owned_albums(page))
+        .await?
+        .into_iter()
+        .chain(
+            client
+                .all_pages(PAGE_SIZE, |c, page| c.list_shared_with_me_albums(page))
+                .await?,
+        )
+        .map(Some)
+        .collect();
+    let mut albums = vec![];
+    for album_name in album_names {
+        if is_pattern(album_name) {
+            let matches = name_matcher(album_name)?;
+            let count = albums.len();
+            for slot in normal_albums.iter_mut() {
+                if slot.as_ref().is_some_and(|a| matches(&a.name)) {
+                    albums.push(Album::Normal(slot.take().unwrap()));
+                }
+            }
+            if albums.len() == count {
+                writeln!(io.stdout(), "No albums matching '{album_name}'")?;
+            }
+            continue;
+        }
+        let normal_album = normal_albums.iter_mut().find(|slot| {
+            slot.as_ref()
+                .is_some_and(|a| a.name.eq_ignore_ascii_case(album_name))
+        });
+        if let Some(slot) = normal_album {
+            albums.push(Album::Normal(slot.take().unwrap()));
+            continue;
+        }
+        /* Already matched by a pattern or an earlier name */
+        if albums
+            .iter()
+            .any(|a| matches!(a, Album::Normal(_)) && a.name().eq_ignore_ascii_case(album_name))
+        {
+            continue;
+        }
+        /* Person or place */
+        match find_album(album_name, user_settings, team_space_settings, client).await? {
+            Some(album) => albums.push(album),
+            None => writeln!(io.stdout(), "Album '{album_name}' not found")?,
+        }
+    }
+    Ok(albums)
+}
+
+/// Subfolder of the target folder for each album, named after it. Albums sharing a name, e.g. an
+/// owned album and one shared with me, or an album and a person, would be exported to the same
+/// subfolder with the same sync state, so they are rejected.
+fn subfolder_paths(albums: &[Album], folder_path: &str) -> Result<Vec<String>> {
+    let names: Vec<String> = albums
+        .iter()
+        .map(|album| album.name().trim().replace('/', "_"))
+        .collect();
+    for (i, name) in names.iter().enumerate() {
+        let same_name_count = names[i..]
+            .iter()
+            .filter(|other| other.to_lowercase() == name.to_lowercase())
+            .count();
+        if same_name_count > 1 {
+            bail!(
+                "{same_name_count} matching albums are named '{name}' and would be exported to \
+                 the same subfolder; export them one at a time to separate folders"
+            )
+        }
+    }
+    let folder_path = folder_path.trim_end_matches('/');
+    Ok(names
+        .into_iter()
+        .map(|name| format!("{folder_path}/{name}"))
+        .collect())
+}
+
+/// Print the summary of each album and their total, which is returned. Fails when export of any
+/// album failed.
+fn print_combined_summary<I: Io>(
+    results: &[(&str, Result<Option<ExportSummary>>)],
+    dry_run: bool,
+    io: &mut I,
+) -> Result<ExportSummary> {
+    let mut total = ExportSummary::default();
+    let mut errors = 0;
+    writeln!(io.stdout(), "Summary of {} album(s):", results.len())?;
+    for (album_name, result) in results {
+        match result {
+            Ok(Some(summary)) => {
+                writeln!(io.stdout(), "- '{album_name}': {summary}")?;
+                total += *summary;
             }
-            DsmError::Photo(PhotoError::NoAccessOrNotFound) => {
-                bail!("folder '{target_folder_path}' does not exist in Personal Space")
+            Ok(None) if dry_run => writeln!(io.stdout(), "- '{album_name}': dry run")?,
+            Ok(None) => writeln!(io.stdout(), "- '{album_name}': copy tasks scheduled")?,
+            Err(error) => {
+                writeln!(io.stdout(), "- '{album_name}': error: {error}")?;
+                errors += 1;
             }
+        }
+    }
+    writeln!(io.stdout(), "Total: {total}")?;
+    if errors != 0 {
+        bail!("export of {errors} album(s) failed")
+    }
+    Ok(total)
+}
+
+/// Target folder, `None` when it does not exist
+async fn find_folder<C: ApiClient>(
+    (folder_path, space): (&str, Space),
+    client: &SessionClient<'_, C>,
+) -> Result<Option<Folder>> {
+    match client.get_folder_by_name((folder_path, space)).await {
+        Ok(folder) => Ok(Some(folder)),
+        Err(error) => match error.downcast::<DsmError>()? {
+            DsmError::Photo(PhotoError::NoAccessOrNotFound) => Ok(None),
             other => bail!(other),
         },
-    };
+    }
+}
 
-    match find_album_future.await? {
-        Some(album) => export((album, folder, user_settings), &client, io).await,
-        None => {
-            let matching_albums = client.suggest_albums(album_name).await.unwrap_or_else(|e| {
-                log::warn!("suggest album search error: {e}");
-                vec![]
-            });
-            album_not_found(album_name, matching_albums, io)
+/// Fail early when the user cannot add items to `space`
+fn check_write_access(
+    space: Space,
+    user_settings: &UserSettings,
+    team_space_settings: &TeamSpaceSettings,
+) -> Result<()> {
+    match space {
+        Space::Personal if !user_settings.enable_home_service => {
+            bail!(
+                "home service not enabled on DSM, Personal Space not available in Synology Photos"
+            )
         }
+        Space::Shared if !team_space_settings.enabled => {
+            bail!("Shared Space not enabled in Synology Photos")
+        }
+        /* Viewers and downloaders can browse Shared Space but not add items to it */
+        Space::Shared
+            if matches!(
+                user_settings.team_space_permission.as_str(),
+                "none" | "view" | "download"
+            ) =>
+        {
+            bail!("you don't have permission to upload to Shared Space")
+        }
+        _ => Ok(()),
     }
 }
 
 async fn create_folder_path<C: ApiClient>(
-    folder_path: &str,
+    (folder_path, space): (&str, Space),
     client: &SessionClient<'_, C>,
 ) -> Result<Folder> {
     let path_segments: Vec<_> = folder_path.split('/').filter(|s| !s.is_empty()).collect();
     if path_segments.iter().any(|s| s.trim().is_empty()) {
         bail!("{folder_path} is not valid folder path");
     }
-    let mut result_folder = client.get_folder_by_name("/").await?;
+    let mut result_folder = client.get_folder_by_name(("/", space)).await?;
     let mut path_so_far = String::new();
     let mut exists = true;
     for segment in path_segments {
         path_so_far.push('/');
         path_so_far.push_str(segment);
         if exists {
-            let folder_result = client.get_folder_by_name(path_so_far.as_str()).await;
+            let folder_result = client
+                .get_folder_by_name((path_so_far.as_str(), space))
+                .await;
             match folder_result {
                 Ok(folder) => result_folder = folder,
                 Err(error) => match error.downcast::<DsmError>()? {
@@ -94,7 +429,9 @@ async fn create_folder_path<C: ApiClient>(
             }
         }
         if !exists {
-            result_folder = client.create_folder(segment, result_folder.id).await?;
+            result_folder = client
+                .create_folder((segment, space), result_folder.id)
+                .await?;
             log::info!("created {path_so_far} folder")
         }
     }
@@ -102,41 +439,373 @@ async fn create_folder_path<C: ApiClient>(
 }
 
 async fn export<C: ApiClient, I: Io>(
-    (album, target_folder, user_settings): (Album, Folder, UserSettings),
+    (album, (target_folder, target_space), user_settings): (&Album, (Folder, Space), &UserSettings),
+    (on_conflict, detach): (OnConflict, bool),
     client: &SessionClient<'_, C>,
     io: &mut I,
-) -> Result<()> {
-    debug_assert!(user_settings.enable_home_service);
-    let photos = client.list_items(&album, album.item_count()).await?;
+) -> Result<Option<ExportSummary>> {
+    let photos = client.list_all_items(album).await?;
     writeln!(
         io.stdout(),
-        "Copying {} items from album '{}' to folder '{}' in Personal Space",
+        "Copying {} items from album '{}' to folder '{}' in {target_space}",
         photos.len(),
         album.name(),
         target_folder.name
     )?;
-
-    let copy_personal_space_photos_future =
-        copy_personal_space_photos(&photos, target_folder.id, client);
-    let copy_shared_space_photos_future =
-        copy_shared_space_photos(&photos, target_folder.id, &user_settings, client);
-
-    process_task_info(
-        vec![
-            copy_personal_space_photos_future.await,
-            copy_shared_space_photos_future.await,
-        ],
+    if detach {
+        let task_info_results = schedule_copy_tasks(
+            &photos,
+            (&target_folder, on_conflict),
+            user_settings,
+            client,
+        )
+        .await;
+        report_scheduled_tasks(task_info_results, io)?;
+        return Ok(None);
+    }
+    let summary = copy_photos(
+        &photos,
+        (&target_folder, on_conflict),
+        user_settings,
         client,
         io,
     )
     .await?;
+    Ok(Some(summary))
+}
+
+/// Export only the items that have not been exported by previous runs, according to sync state
+/// file. Items removed from the album since the last run are reported, and with `prune` also
+/// deleted from the target folder.
+async fn sync_export<C: ApiClient, I: Io, F: Fs>(
+    (album, (target_folder, target_space), user_settings): (&Album, (Folder, Space), &UserSettings),
+    (state_key, on_conflict): ((&str, &str, &str), OnConflict),
+    prune: bool,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+    fs: &F,
+) -> Result<ExportSummary> {
+    let mut state = SyncState::try_load(fs)?;
+    let photos = client.list_all_items(album).await?;
+    let mut album_filenames: HashMap<String, usize> = HashMap::new();
+    for photo in &photos {
+        *album_filenames.entry(photo.filename.clone()).or_default() += 1;
+    }
+    let (new_photos, removed_photos) = sync_changes(photos, &state, state_key);
+
+    writeln!(
+        io.stdout(),
+        "Copying {} new items from album '{}' to folder '{}' in {target_space}",
+        new_photos.len(),
+        album.name(),
+        target_folder.name
+    )?;
+    let mut summary = ExportSummary::default();
+    if !new_photos.is_empty() {
+        let target = (&target_folder, target_space);
+        let existing_items = list_folder_items(target, client).await?;
+        summary = copy_photos(
+            &new_photos,
+            (&target_folder, on_conflict),
+            user_settings,
+            client,
+            io,
+        )
+        .await?;
+        /* Task info does not tell which items failed, so the new items are only recorded when
+         * everything went well. Otherwise, they will be retried on the next run (existing files
+         * are skipped). */
+        if summary.is_complete() {
+            let shared_space_access = user_settings.team_space_permission != "none";
+            let new_photos: Vec<_> = new_photos
+                .into_iter()
+                .filter(|p| p.owner_user_id != 0 || shared_space_access)
+                .collect();
+            let copies = identify_copies(
+                (&new_photos, &album_filenames),
+                &existing_items,
+                list_folder_items(target, client).await?,
+            );
+            let entry = state.entry_mut(state_key);
+            entry.copies.extend(copies);
+            for photo in new_photos {
+                entry.items.insert(photo.id, photo.filename);
+            }
+        }
+    }
+
+    if !removed_photos.is_empty() {
+        writeln!(
+            io.stdout(),
+            "{} item(s) removed from album since the last run:",
+            removed_photos.len()
+        )?;
+        for (_, filename) in &removed_photos {
+            writeln!(io.stdout(), "- {filename}")?;
+        }
+        let entry = state.entry_mut(state_key);
+        if prune {
+            let (pruned, kept) = prune_photos(
+                (&removed_photos, &entry.copies),
+                &album_filenames,
+                (&target_folder, target_space),
+                client,
+            )
+            .await?;
+            writeln!(io.stdout(), "Deleted {pruned} item(s) from target folder")?;
+            if kept != 0 {
+                writeln!(
+                    io.stdout(),
+                    "Kept {kept} item(s) which could not be identified as exported copies, or \
+                     share a file name with an item still in the album"
+                )?;
+            }
+        }
+        for (id, _) in removed_photos {
+            entry.items.remove(&id);
+            entry.copies.remove(&id);
+        }
+    }
+    state.try_save(fs)?;
+    Ok(summary)
+}
+
+async fn list_folder_items<C: ApiClient>(
+    (folder, space): (&Folder, Space),
+    client: &SessionClient<'_, C>,
+) -> Result<Vec<Item>> {
+    client
+        .all_pages(PAGE_SIZE, |c, page| {
+            c.list_folder_items((folder.id, space), page)
+        })
+        .await
+}
+
+/// Match `new_photos` with their copies, i.e. items which appeared in the target folder during the
+/// export (`existing_items` were there before it). A copy is only recorded when its file name is
+/// not shared by another album item, so that it cannot be mistaken for a different one.
+fn identify_copies(
+    (new_photos, album_filenames): (&[Item], &HashMap<String, usize>),
+    existing_items: &[Item],
+    folder_items: Vec<Item>,
+) -> Vec<(u32, u32)> {
+    let existing_ids: HashSet<u32> = existing_items.iter().map(|i| i.id).collect();
+    let existing_names: HashSet<&str> =
+        existing_items.iter().map(|i| i.filename.as_str()).collect();
+    let copies: HashMap<String, u32> = folder_items
+        .into_iter()
+        .filter(|i| !existing_ids.contains(&i.id) && !existing_names.contains(i.filename.as_str()))
+        .map(|i| (i.filename, i.id))
+        .collect();
+    new_photos
+        .iter()
+        .filter(|p| album_filenames.get(&p.filename) == Some(&1))
+        .filter_map(|p| copies.get(&p.filename).map(|copy_id| (p.id, *copy_id)))
+        .collect()
+}
 
+/// Split album `photos` into the ones not exported yet and (id, file name) pairs of exported items
+/// no longer in the album
+fn sync_changes(
+    photos: Vec<Item>,
+    state: &SyncState,
+    state_key: (&str, &str, &str),
+) -> (Vec<Item>, Vec<(u32, String)>) {
+    let album_ids: HashSet<u32> = photos.iter().map(|p| p.id).collect();
+    match state.entry(state_key).map(|e| &e.items) {
+        Some(exported) => (
+            photos
+                .into_iter()
+                .filter(|p| !exported.contains_key(&p.id))
+                .collect(),
+            exported
+                .iter()
+                .filter(|(id, _)| !album_ids.contains(id))
+                .map(|(id, filename)| (*id, filename.clone()))
+                .collect(),
+        ),
+        None => (photos, vec![]),
+    }
+}
+
+/// Report what an export would do: whether the target folder would be created and which items
+/// would be copied from each space. With `state_key` (sync mode), only the items not exported yet
+/// are considered. Nothing is created, copied or deleted.
+async fn preview<C: ApiClient, I: Io, F: Fs>(
+    (album, (target_folder, folder_path, target_space), user_settings): (
+        &Album,
+        (Option<&Folder>, &str, Space),
+        &UserSettings,
+    ),
+    (state_key, on_conflict, prune): (Option<(&str, &str, &str)>, OnConflict, bool),
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+    fs: &F,
+) -> Result<()> {
+    let photos = client.list_all_items(album).await?;
+    let (photos, removed_photos) = match state_key {
+        Some(state_key) => sync_changes(photos, &SyncState::try_load(fs)?, state_key),
+        None => (photos, vec![]),
+    };
+
+    let out = io.stdout();
+    writeln!(out, "Dry run, nothing will be changed")?;
+    match target_folder {
+        Some(folder) => writeln!(
+            out,
+            "Target folder '{}' exists in {target_space}",
+            folder.name
+        )?,
+        None => writeln!(
+            out,
+            "Target folder '{folder_path}' would be created in {target_space}"
+        )?,
+    }
+
+    let (personal_space_photos, shared_space_photos): (Vec<_>, Vec<_>) =
+        photos.iter().partition(|p| p.owner_user_id != 0);
+    let shared_space_access = user_settings.team_space_permission != "none";
+    let count = if shared_space_access {
+        personal_space_photos.len() + shared_space_photos.len()
+    } else {
+        personal_space_photos.len()
+    };
+    let new = if state_key.is_some() { "new " } else { "" };
+    writeln!(
+        out,
+        "Would copy {count} {new}items from album '{}' (on conflict: {on_conflict}):",
+        album.name()
+    )?;
+    writeln!(
+        out,
+        "- {} item(s) from Personal Space",
+        personal_space_photos.len()
+    )?;
+    if shared_space_access {
+        writeln!(
+            out,
+            "- {} item(s) from Shared Space",
+            shared_space_photos.len()
+        )?;
+    } else if !shared_space_photos.is_empty() {
+        writeln!(
+            out,
+            "Would skip {} item(s) from Shared Space, which you don't have access to:",
+            shared_space_photos.len()
+        )?;
+        for photo in shared_space_photos {
+            writeln!(out, "- {}", photo.filename)?;
+        }
+    }
+
+    if !removed_photos.is_empty() {
+        writeln!(
+            out,
+            "{} item(s) removed from album since the last run{}:",
+            removed_photos.len(),
+            if prune {
+                "; their recorded copies would be deleted from target folder"
+            } else {
+                ""
+            }
+        )?;
+        for (_, filename) in &removed_photos {
+            writeln!(out, "- {filename}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete copies of `removed_photos` from `target_folder`. Only copies recorded in sync state are
+/// deleted, and only when they still have the original file name which no item currently in the
+/// album has (`album_filenames`), so that files not created by export are never deleted. Returns
+/// numbers of deleted and kept items.
+async fn prune_photos<C: ApiClient>(
+    (removed_photos, copies): (&[(u32, String)], &BTreeMap<u32, u32>),
+    album_filenames: &HashMap<String, usize>,
+    (target_folder, target_space): (&Folder, Space),
+    client: &SessionClient<'_, C>,
+) -> Result<(usize, usize)> {
+    let folder_items: HashMap<u32, String> =
+        list_folder_items((target_folder, target_space), client)
+            .await?
+            .into_iter()
+            .map(|i| (i.id, i.filename))
+            .collect();
+    let ids: Vec<u32> = removed_photos
+        .iter()
+        .filter(|(_, filename)| !album_filenames.contains_key(filename))
+        .filter_map(|(id, filename)| {
+            copies
+                .get(id)
+                .filter(|copy_id| folder_items.get(copy_id) == Some(filename))
+                .copied()
+        })
+        .collect();
+    if !ids.is_empty() {
+        client.delete_items((&ids, target_space)).await?;
+    }
+    Ok((ids.len(), removed_photos.len() - ids.len()))
+}
+
+/// Schedule copy tasks for Personal and Shared Space items and wait for them to finish
+async fn copy_photos<C: ApiClient, I: Io>(
+    photos: &[Item],
+    (target_folder, on_conflict): (&Folder, OnConflict),
+    user_settings: &UserSettings,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+) -> Result<ExportSummary> {
+    let task_info_results =
+        schedule_copy_tasks(photos, (target_folder, on_conflict), user_settings, client).await;
+    process_task_info(task_info_results, client, io).await
+}
+
+async fn schedule_copy_tasks<C: ApiClient>(
+    photos: &[Item],
+    (target_folder, on_conflict): (&Folder, OnConflict),
+    user_settings: &UserSettings,
+    client: &SessionClient<'_, C>,
+) -> Vec<Result<Option<TaskInfo>>> {
+    let target = (target_folder.id, on_conflict);
+    let copy_personal_space_photos_future = copy_personal_space_photos(photos, target, client);
+    let copy_shared_space_photos_future =
+        copy_shared_space_photos(photos, target, user_settings, client);
+    vec![
+        copy_personal_space_photos_future.await,
+        copy_shared_space_photos_future.await,
+    ]
+}
+
+/// Report ids of scheduled copy tasks instead of waiting for them, see the 'tasks' command
+fn report_scheduled_tasks<I: Io>(
+    task_info_results: Vec<Result<Option<TaskInfo>>>,
+    io: &mut I,
+) -> Result<()> {
+    let mut task_ids = vec![];
+    for result in task_info_results {
+        match result {
+            Ok(Some(task)) => task_ids.push(task.id.to_string()),
+            Ok(None) => {}
+            Err(error) => writeln!(io.stdout(), "Error: {error}")?,
+        }
+    }
+    if task_ids.is_empty() {
+        writeln!(io.stdout(), "No copy tasks scheduled")?;
+        return Ok(());
+    }
+    let task_ids = task_ids.join(" ");
+    writeln!(io.stdout(), "Scheduled copy task(s): {task_ids}")?;
+    writeln!(
+        io.stdout(),
+        "Use 'tasks status {task_ids}' or 'tasks wait {task_ids}' to follow them"
+    )?;
     Ok(())
 }
 
 async fn copy_personal_space_photos<C: ApiClient>(
     photos: &[Item],
-    target_folder_id: u32,
+    target: (u32, OnConflict),
     client: &SessionClient<'_, C>,
 ) -> Result<Option<TaskInfo>> {
     let personal_space_photo_ids: Vec<_> = photos
@@ -148,14 +817,14 @@ async fn copy_personal_space_photos<C: ApiClient>(
         return Ok(None);
     }
     let task_info = client
-        .copy_photos(&personal_space_photo_ids, Space::Personal, target_folder_id)
+        .copy_photos(&personal_space_photo_ids, Space::Personal, target)
         .await?;
     Ok(Some(task_info))
 }
 
 async fn copy_shared_space_photos<C: ApiClient>(
     photos: &[Item],
-    target_folder_id: u32,
+    target: (u32, OnConflict),
     user_settings: &UserSettings,
     client: &SessionClient<'_, C>,
 ) -> Result<Option<TaskInfo>> {
@@ -177,74 +846,557 @@ async fn copy_shared_space_photos<C: ApiClient>(
     }
 
     let task_info = client
-        .copy_photos(&shared_space_photo_ids, Space::Shared, target_folder_id)
+        .copy_photos(&shared_space_photo_ids, Space::Shared, target)
         .await?;
     Ok(Some(task_info))
 }
 
+/// Action for photos already present in the target folder, passed to the copy task
+#[derive(Debug, Default, Display, Copy, Clone, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+    /// Keep the existing file
+    #[default]
+    #[display("skip")]
+    Skip,
+    /// Replace the existing file
+    #[display("overwrite")]
+    Overwrite,
+    /// Copy under a new name, keeping the existing file
+    #[display("rename")]
+    Rename,
+}
+
+/// Item counts of finished copy tasks
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ExportSummary {
+    pub copied: u32,
+    pub skipped: u32,
+    /// Copied items which replaced or were renamed next to existing files, included in `copied`
+    pub conflicts: u32,
+    pub failed: u32,
+    pub aborted: u32,
+    /// Number of copy tasks which could not be scheduled
+    pub task_errors: usize,
+    /// Waiting for copy tasks was interrupted with Ctrl-C
+    pub interrupted: bool,
+}
+
+impl ExportSummary {
+    /// Whether all items were either copied or skipped
+    pub fn is_complete(&self) -> bool {
+        self.failed == 0 && self.aborted == 0 && self.task_errors == 0 && !self.interrupted
+    }
+}
+
+impl AddAssign for ExportSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.copied += other.copied;
+        self.skipped += other.skipped;
+        self.conflicts += other.conflicts;
+        self.failed += other.failed;
+        self.aborted += other.aborted;
+        self.task_errors += other.task_errors;
+        self.interrupted |= other.interrupted;
+    }
+}
+
+impl Display for ExportSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} copied ({} conflicting, overwritten or renamed), {} skipped, {} failed, {} canceled",
+            self.copied, self.conflicts, self.skipped, self.failed, self.aborted
+        )
+    }
+}
+
 /// Wait for copy tasks to finish, reporting results. This requires polling the API.
 async fn process_task_info<C: ApiClient, I: Io>(
     task_info_results: Vec<Result<Option<TaskInfo>>>,
     client: &SessionClient<'_, C>,
     io: &mut I,
-) -> Result<()> {
+) -> Result<ExportSummary> {
     let (tasks, errs): (Vec<_>, Vec<_>) = task_info_results.into_iter().partition(|r| r.is_ok());
+    let task_errors = errs.len();
     for error in errs.into_iter().map(Result::unwrap_err) {
         writeln!(io.stdout(), "Error: {error}")?;
     }
-    let mut task_ids: Vec<_> = tasks
+    let task_ids: Vec<_> = tasks
         .into_iter()
         .filter_map(Result::unwrap)
         .map(|t| t.id)
         .collect();
-    let (mut copied, mut skipped, mut failed, mut aborted) = (0, 0, 0, 0);
-    let mut dot_print_counter = 0;
-    loop {
-        if task_ids.is_empty() {
-            writeln!(io.stdout())?;
-            break;
-        }
-        sleep(Duration::from_secs(1)).await;
-
-        if dot_print_counter == 3 {
-            write!(io.stdout(), "\r   \r")?;
-            dot_print_counter = 0;
-        } else {
-            write!(io.stdout(), ".")?;
-            dot_print_counter += 1;
-        }
-        io.stdout().flush()?;
+    let finished = interrupt::interruptible(wait_for_tasks(task_ids.clone(), client, io))
+        .await
+        .transpose()?;
+    let interrupted = finished.is_none();
+    let task_infos = match finished {
+        Some(task_infos) => task_infos,
+        None => handle_interrupt(&task_ids, client, io).await?,
+    };
+    let summary = ExportSummary {
+        task_errors,
+        interrupted,
+        ..summarize_tasks(&task_infos)
+    };
+    if summary.failed != 0 {
+        log::warn!(
+            "export failed ({} item(s) not copied); \
+            inspect Synology Photos web interface for details",
+            summary.failed
+        );
+    }
+    if interrupted {
+        writeln!(io.stdout(), "Export summary (so far): {summary}")?;
+    } else {
+        writeln!(io.stdout(), "Export summary: {summary}")?;
+    }
+    Ok(summary)
+}
 
-        let updated_task_infos = client.get_task_status(&task_ids).await?;
-        let (done, processing): (Vec<_>, Vec<_>) = updated_task_infos.iter().partition(|t| {
-            t.status != "waiting" && t.status != "processing" && t.status != "aborting"
-        });
-        task_ids.clear();
-        task_ids.append(&mut processing.into_iter().map(|t| t.id).collect::<Vec<u32>>());
+/// Add up item counts of copy tasks. Completion counts all processed items, including skipped
+/// and failed ones. Task info only tells how many conflicting items were not skipped, not whether
+/// they were overwritten or renamed, so they are reported as conflicts.
+fn summarize_tasks(task_infos: &[TaskInfo]) -> ExportSummary {
+    let mut summary = ExportSummary::default();
+    for t in task_infos {
+        let copied = t.completion.checked_sub(t.skip + t.error);
+        let aborted = match is_running(t) {
+            true => Some(0),
+            false => t.total.checked_sub(t.completion),
+        };
+        let (Some(copied), Some(aborted)) = (copied, aborted) else {
+            log::warn!(
+                "inconsistent item counts reported for copy task {}: {t:?}",
+                t.id
+            );
+            continue;
+        };
+        summary.copied += copied;
+        summary.conflicts += t.overwrite;
+        summary.skipped += t.skip;
+        summary.failed += t.error;
+        summary.aborted += aborted;
+    }
+    summary
+}
 
-        for t in done {
-            copied += t.completion - t.skip - t.error;
-            skipped += t.skip;
-            failed += t.error;
-            aborted += t.total - (t.completion + t.skip + t.error)
-        }
+/// Offer to abort the tasks still running after Ctrl-C. Another Ctrl-C while asked exits. When
+/// `io` cannot prompt (e.g. output of parallel jobs), the tasks are left running. Returns their
+/// current task infos.
+async fn handle_interrupt<C: ApiClient, I: Io>(
+    task_ids: &[u32],
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+) -> Result<Vec<TaskInfo>> {
+    writeln!(io.stdout())?;
+    if task_ids.is_empty() {
+        return Ok(vec![]);
     }
-    if failed != 0 {
-        log::warn!(
-            "export failed ({failed} item(s) not copied); \
-            inspect Synology Photos web interface for details"
-        );
+    let task_infos = client.get_task_status(task_ids).await?;
+    let running_ids: Vec<u32> = task_infos
+        .iter()
+        .filter(|t| is_running(t))
+        .map(|t| t.id)
+        .collect();
+    if running_ids.is_empty() {
+        return Ok(task_infos);
+    }
+    let answer = match io.can_prompt() {
+        true => read_input(
+            format!(
+                "Interrupted; abort {} running copy task(s)? \
+                Otherwise they keep running on DSM [y/N]",
+                running_ids.len()
+            )
+            .as_str(),
+            io,
+        )?,
+        false => String::new(),
+    };
+    if answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes") {
+        client.abort_tasks(&running_ids).await?;
+        writeln!(io.stdout(), "Aborting copy task(s)")?;
+        /* Items processed while the prompt was shown are included */
+        return client.get_task_status(task_ids).await;
     }
+    let ids = running_ids
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
     writeln!(
         io.stdout(),
-        "Export summary: {copied} copied, {skipped} skipped, {failed} failed, {aborted} canceled"
+        "Copy task(s) left running, use 'tasks wait {ids}' to follow them"
     )?;
-    Ok(())
+    Ok(task_infos)
 }
 
 #[cfg(test)]
 mod test {
-    use std::time::Duration;
+    use super::*;
+    use crate::io::BufferIo;
+    use crate::test::{FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use std::sync::{Arc, Mutex};
+    use syno_api::foto;
+
+    /// Client for task 7, half done when Ctrl-C is pressed, which records abort requests
+    fn task_client(aborts: Arc<Mutex<Vec<String>>>) -> FakeHttpClient {
+        FakeHttpClient::new(move |request| {
+            let aborted = !aborts.lock().unwrap().is_empty();
+            if request.is(foto::background_task::info::API, "abort") {
+                aborts.lock().unwrap().push(request.param("id").unwrap());
+                return FakeResponse::data(());
+            }
+            assert!(request.is(foto::background_task::info::API, "get_status"));
+            let task = match aborted {
+                false => TaskInfo {
+                    id: 7,
+                    status: "processing".to_string(),
+                    total: 10,
+                    completion: 4,
+                    skip: 1,
+                    ..TaskInfo::default()
+                },
+                /* One more item processed while the prompt was shown */
+                true => TaskInfo {
+                    id: 7,
+                    status: "aborted".to_string(),
+                    total: 10,
+                    completion: 5,
+                    skip: 1,
+                    ..TaskInfo::default()
+                },
+            };
+            FakeResponse::data(serde_json::json!({ "list": [task] }))
+        })
+    }
+
+    #[tokio::test]
+    async fn handle_interrupt_aborts_running_tasks() {
+        let aborts = Arc::new(Mutex::new(vec![]));
+        let client = task_client(aborts.clone());
+        let conf = logged_in_conf();
+        let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+        let mut io = FakeIo::new("y\n");
+
+        let task_infos = handle_interrupt(&[7], &client, &mut io).await.unwrap();
+
+        assert_eq!(*aborts.lock().unwrap(), ["[7]"]);
+        assert!(io.stdout_str().contains("Aborting copy task(s)"));
+        let summary = summarize_tasks(&task_infos);
+        assert_eq!(
+            (summary.copied, summary.skipped, summary.aborted),
+            (4, 1, 5)
+        );
+    }
+
+    #[tokio::test]
+    async fn handle_interrupt_leaves_tasks_running() {
+        let aborts = Arc::new(Mutex::new(vec![]));
+        let client = task_client(aborts.clone());
+        let conf = logged_in_conf();
+        let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+        let mut io = FakeIo::new("\n");
+
+        let task_infos = handle_interrupt(&[7], &client, &mut io).await.unwrap();
+
+        assert!(aborts.lock().unwrap().is_empty());
+        assert!(
+            io.stdout_str()
+                .contains("Copy task(s) left running, use 'tasks wait 7' to follow them")
+        );
+        /* Items not processed yet are not counted as canceled */
+        let summary = summarize_tasks(&task_infos);
+        assert_eq!(
+            (summary.copied, summary.skipped, summary.aborted),
+            (3, 1, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn handle_interrupt_without_prompt_leaves_tasks_running() {
+        let aborts = Arc::new(Mutex::new(vec![]));
+        let client = task_client(aborts.clone());
+        let conf = logged_in_conf();
+        let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+        let mut io = BufferIo::new();
+
+        handle_interrupt(&[7], &client, &mut io).await.unwrap();
+
+        assert!(aborts.lock().unwrap().is_empty());
+        let mut output = FakeIo::new("");
+        io.flush_to(&mut output).unwrap();
+        assert_eq!(
+            output.stdout_str(),
+            "\nCopy task(s) left running, use 'tasks wait 7' to follow them\n"
+        );
+    }
+
+    #[test]
+    fn album_names_with_glob_or_regex_prefix_are_patterns() {
+        assert!(is_pattern("glob:Holidays *"));
+        assert!(is_pattern("glob:Holiday?"));
+        assert!(is_pattern("re:^holidays 20\\d\\d$"));
+        assert!(!is_pattern("Holidays 2024"));
+        assert!(!is_pattern("Who's there?"));
+        assert!(!is_pattern("Best of *"));
+        assert!(!is_pattern("Free: re:"));
+    }
 
-    pub(super) async fn fake_sleep(_: Duration) {}
+    #[tokio::test]
+    async fn find_albums_matches_patterns_and_names() {
+        use syno_api::foto::browse::person::dto::Person;
+
+        let album = |id, name: &str| AlbumDto {
+            id,
+            name: name.to_string(),
+            ..AlbumDto::default()
+        };
+        let client = FakeHttpClient::new(move |request| {
+            let list = match request.param("offset").as_deref() {
+                Some("0") if request.is(foto::browse::album::API, "list") => serde_json::json!([
+                    album(1, "Holidays 2023"),
+                    album(2, "Work"),
+                    album(4, "Old holidays"),
+                    album(5, "Who's there?"),
+                    album(6, "Whose tent")
+                ]),
+                Some("0") if request.is(foto::sharing::misc::API, "list_shared_with_me_album") => {
+                    serde_json::json!([album(3, "Holidays 2024")])
+                }
+                Some("0") if request.is(foto::browse::person::API, "list") => {
+                    serde_json::json!([Person {
+                        id: 9,
+                        name: "Alice".to_string(),
+                        ..Person::default()
+                    }])
+                }
+                _ => serde_json::json!([]),
+            };
+            FakeResponse::data(serde_json::json!({ "list": list }))
+        });
+        let conf = logged_in_conf();
+        let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+        let user_settings = UserSettings {
+            enable_person: true,
+            ..UserSettings::default()
+        };
+        let album_names = [
+            "re:^holidays",
+            "holidays 2023",
+            "Alice",
+            "Who's there?",
+            "Nobody",
+            "glob:X*",
+        ]
+        .map(String::from);
+        let mut io = FakeIo::new("");
+
+        let albums = find_albums(
+            &album_names,
+            (&user_settings, &TeamSpaceSettings::default()),
+            &client,
+            &mut io,
+        )
+        .await
+        .unwrap();
+
+        let ids: Vec<_> = albums.iter().map(Album::id_param).collect();
+        assert_eq!(
+            ids,
+            [
+                ("album_id", "1".to_string()),
+                ("album_id", "3".to_string()),
+                ("person_id", "9".to_string()),
+                ("album_id", "5".to_string())
+            ]
+        );
+        assert_eq!(
+            io.stdout_str(),
+            "Album 'Nobody' not found\nNo albums matching 'glob:X*'\n"
+        );
+
+        let invalid = find_albums(
+            &["re:(".to_string()],
+            (&user_settings, &TeamSpaceSettings::default()),
+            &client,
+            &mut io,
+        )
+        .await;
+        assert!(
+            invalid
+                .unwrap_err()
+                .to_string()
+                .starts_with("invalid regular expression '('")
+        );
+    }
+
+    #[test]
+    fn albums_sharing_a_name_are_not_exported_together() {
+        let album = |id, name: &str| {
+            Album::Normal(AlbumDto {
+                id,
+                name: name.to_string(),
+                ..AlbumDto::default()
+            })
+        };
+
+        assert_eq!(
+            subfolder_paths(&[album(1, "Trips"), album(2, "a/b")], "/Exports/").unwrap(),
+            ["/Exports/Trips", "/Exports/a_b"]
+        );
+        let error = subfolder_paths(&[album(1, "Trips"), album(2, "trips ")], "/Exports")
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("2 matching albums are named 'Trips'"));
+    }
+
+    #[test]
+    fn combined_summary_reports_each_album_once() {
+        let summary = ExportSummary {
+            copied: 3,
+            skipped: 1,
+            ..ExportSummary::default()
+        };
+        let results = [
+            ("Trips", Ok(Some(summary))),
+            ("Work", Ok(None)),
+            ("Gone", Err(anyhow!("album was deleted"))),
+            ("Pets", Ok(Some(summary))),
+        ];
+        let mut io = FakeIo::new("");
+
+        let result = print_combined_summary(&results, false, &mut io);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "export of 1 album(s) failed"
+        );
+        assert_eq!(
+            io.stdout_str(),
+            "Summary of 4 album(s):\n\
+             - 'Trips': 3 copied (0 conflicting, overwritten or renamed), 1 skipped, 0 failed, 0 canceled\n\
+             - 'Work': copy tasks scheduled\n\
+             - 'Gone': error: album was deleted\n\
+             - 'Pets': 3 copied (0 conflicting, overwritten or renamed), 1 skipped, 0 failed, 0 canceled\n\
+             Total: 6 copied (0 conflicting, overwritten or renamed), 2 skipped, 0 failed, 0 canceled\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn prune_deletes_only_recorded_copies_with_unique_names() {
+        let folder_items = [
+            (100, "a.jpg"), // copy of removed item 1
+            (101, "b.jpg"), // copy of removed item 2, but "b.jpg" is also a current album item
+            (102, "c.jpg"), // not a recorded copy, e.g. added by the user
+            (103, "e.jpg"), // recorded copy of item 5, renamed since
+        ];
+        let deleted = Arc::new(Mutex::new(vec![]));
+        let deleted_ids = deleted.clone();
+        let client = FakeHttpClient::new(move |request| {
+            if request.is("SYNO.Foto.Browse.Item", "delete") {
+                deleted_ids
+                    .lock()
+                    .unwrap()
+                    .push(request.param("id").unwrap());
+                return FakeResponse::data(());
+            }
+            assert!(request.is("SYNO.Foto.Browse.Item", "list"));
+            let items: Vec<_> = folder_items
+                .iter()
+                .filter(|_| request.param("offset").unwrap() == "0")
+                .map(|&(id, filename)| Item {
+                    id,
+                    filename: filename.to_string(),
+                    ..Item::default()
+                })
+                .collect();
+            FakeResponse::data(serde_json::json!({ "list": items }))
+        });
+        let conf = logged_in_conf();
+        let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+        let removed_photos = [
+            (1, "a.jpg".to_string()),
+            (2, "b.jpg".to_string()),
+            (3, "c.jpg".to_string()),
+            (4, "d.jpg".to_string()),
+            (5, "f.jpg".to_string()),
+        ];
+        let copies = BTreeMap::from([(1, 100), (2, 101), (4, 104), (5, 103)]);
+        let album_filenames = HashMap::from([("b.jpg".to_string(), 1)]);
+        let folder = Folder {
+            id: 10,
+            ..Folder::default()
+        };
+
+        let (pruned, kept) = prune_photos(
+            (&removed_photos, &copies),
+            &album_filenames,
+            (&folder, Space::Personal),
+            &client,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!((pruned, kept), (1, 4));
+        assert_eq!(*deleted.lock().unwrap(), ["[100]"]);
+    }
+
+    #[test]
+    fn identify_copies_skips_ambiguous_names() {
+        let item = |id, filename: &str| Item {
+            id,
+            filename: filename.to_string(),
+            ..Item::default()
+        };
+        let new_photos = [item(1, "a.jpg"), item(2, "b.jpg"), item(3, "c.jpg")];
+        /* "b.jpg" is shared by two album items */
+        let album_filenames = HashMap::from([
+            ("a.jpg".to_string(), 1),
+            ("b.jpg".to_string(), 2),
+            ("c.jpg".to_string(), 1),
+        ]);
+        /* "c.jpg" was in the folder before the export, so it was not created by it */
+        let existing_items = [item(102, "c.jpg")];
+        let folder_items = vec![item(100, "a.jpg"), item(101, "b.jpg"), item(102, "c.jpg")];
+
+        assert_eq!(
+            identify_copies(
+                (&new_photos, &album_filenames),
+                &existing_items,
+                folder_items
+            ),
+            [(1, 100)]
+        );
+    }
+
+    #[test]
+    fn summarize_tasks_counts_conflicts_among_copied_items() {
+        let task = |status: &str, (total, completion, skip, error, overwrite)| TaskInfo {
+            status: status.to_string(),
+            total,
+            completion,
+            skip,
+            error,
+            overwrite,
+            ..TaskInfo::default()
+        };
+        let summary = summarize_tasks(&[
+            task("done", (10, 10, 2, 1, 3)),
+            /* Canceled before all items were processed */
+            task("aborted", (8, 5, 1, 0, 0)),
+            /* Remaining items are not counted as canceled while the task runs */
+            task("processing", (6, 2, 0, 0, 1)),
+            /* Counts not adding up are left out instead of being masked */
+            task("done", (4, 4, 3, 2, 0)),
+        ]);
+
+        assert_eq!(
+            (summary.copied, summary.conflicts, summary.skipped),
+            (13, 4, 3)
+        );
+        assert_eq!((summary.failed, summary.aborted), (1, 3));
+    }
 }
@@ -0,0 +1,79 @@
+//! Sync state file ($HOME/.syno-photos-util-sync) support
+//!
+//! Records items already exported from an album to a target folder, so that subsequent runs of
+//! `export --sync` only copy the new ones.
+
+use crate::fs::Fs;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SyncState {
+    entries: Vec<SyncEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SyncEntry {
+    url: String,
+    album: String,
+    folder: String,
+    /// Exported item ids mapped to file names
+    pub items: BTreeMap<u32, String>,
+    /// Exported item ids mapped to ids of their copies in the target folder. Only copies which
+    /// could be identified unambiguously are recorded, and only those are deleted by `--prune`.
+    #[serde(default)]
+    pub copies: BTreeMap<u32, u32>,
+}
+
+impl SyncState {
+    const STATE_FILE: &'static str = ".syno-photos-util-sync";
+
+    /// Items exported so far from `album` to `folder` on DSM at `url`
+    pub fn entry(&self, (url, album, folder): (&str, &str, &str)) -> Option<&SyncEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.url == url && e.album.eq_ignore_ascii_case(album) && e.folder == folder)
+    }
+
+    pub fn entry_mut(&mut self, (url, album, folder): (&str, &str, &str)) -> &mut SyncEntry {
+        let position = self.entries.iter().position(|e| {
+            e.url == url && e.album.eq_ignore_ascii_case(album) && e.folder == folder
+        });
+        let index = position.unwrap_or_else(|| {
+            self.entries.push(SyncEntry {
+                url: url.to_string(),
+                album: album.to_string(),
+                folder: folder.to_string(),
+                items: BTreeMap::new(),
+                copies: BTreeMap::new(),
+            });
+            self.entries.len() - 1
+        });
+        &mut self.entries[index]
+    }
+
+    pub fn try_save<F: Fs>(&self, fs: &F) -> Result<()> {
+        let data = serde_json::to_string(self)?;
+        fs.write(Self::state_path(fs)?, data.as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns empty state when the file does not exist yet
+    pub fn try_load<F: Fs>(fs: &F) -> Result<Self> {
+        let state_path = Self::state_path(fs)?;
+        if !fs.exists(&state_path) {
+            return Ok(Self::default());
+        }
+        let data = fs.read_to_string(&state_path)?;
+        serde_json::from_str(data.as_str())
+            .map_err(|e| anyhow!("unable to read {}: {e}", state_path.display()))
+    }
+
+    fn state_path<F: Fs>(fs: &F) -> Result<PathBuf> {
+        fs.home_dir()
+            .map(|home| home.join(Self::STATE_FILE))
+            .ok_or(anyhow!("unable to find home dir"))
+    }
+}
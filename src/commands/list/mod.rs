@@ -1,10 +1,14 @@
 use crate::commands::api_client::{ApiClient, SessionClient, Space};
 use crate::commands::{DsmError, album_not_found, find_album};
 use crate::conf::Conf;
+use crate::fs::Fs;
 use crate::http::HttpClient;
 use crate::io::Io;
+use crate::output::{OutputFormat, Record, write_records};
 use anyhow::{Context, Result, anyhow, bail};
 use futures::stream::{self, StreamExt};
+use path_mapping::{PathMapper, PathMapping};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use syno_api::foto::error::PhotoError;
@@ -15,16 +19,41 @@ use syno_api::foto::{
 };
 
 mod api_client;
+pub mod path_mapping;
 
-pub async fn handle<C: HttpClient, I: Io>(
+/// `(path_mappings, (volume_paths, homes_path), save)` - path mapping rules, volume paths mode and
+/// location of homes shared folder given on command line, and whether to save them in conf file
+pub type PathMappingArgs = (Vec<PathMapping>, (bool, Option<String>), bool);
+
+pub async fn handle<C: HttpClient, I: Io, F: Fs>(
     album_name: &str,
-    conf: &Conf,
+    (path_mappings, (volume_paths, homes_path), save): PathMappingArgs,
+    output: OutputFormat,
+    conf: &mut Conf,
     client: &C,
     io: &mut I,
+    fs: &F,
 ) -> Result<()> {
     if !conf.is_logged_in() {
         bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
     }
+    if save {
+        conf.path_mappings = path_mappings.clone();
+        conf.volume_paths = volume_paths;
+        conf.homes_path = homes_path.clone();
+        conf.try_save(fs)?;
+    }
+    /* Rules given on command line take precedence over the saved ones */
+    let path_mapper = PathMapper::new(
+        path_mappings
+            .into_iter()
+            .chain(conf.path_mappings.iter().cloned())
+            .collect(),
+        (
+            volume_paths || conf.volume_paths,
+            homes_path.or_else(|| conf.homes_path.clone()),
+        ),
+    );
     let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
     let user_settings = client.get_user_settings().await?;
     let team_space_settings = client.get_team_space_settings().await?;
@@ -33,21 +62,13 @@ pub async fn handle<C: HttpClient, I: Io>(
     match album {
         Some(album) => {
             let photos = client
-                .list_items(&album, album.item_count())
+                .list_all_items(&album)
                 .await
                 .with_context(|| "listing album contents failed")?;
 
-            let folder_ids: HashSet<u32> = photos.iter().map(|p| p.folder_id).collect();
-            let folders_future = get_folder_results(folder_ids, &user_settings, &client);
-
-            let owner_ids: HashSet<u32> = photos.iter().map(|p| p.owner_user_id).collect();
-            let users = client.get_users(&owner_ids).await?;
-            let user_map: HashMap<u32, UserInfo> = users.into_iter().map(|u| (u.id, u)).collect();
-
-            let folder_results = folders_future.await;
-            let photo_to_folder_result_map = map_photo_to_folder_result(photos, &folder_results);
-            print_results(photo_to_folder_result_map, user_map, io)
+            print_locations(photos, (&user_settings, &path_mapper), output, &client, io).await
         }
+        None if output != OutputFormat::Text => bail!("album '{album_name}' not found"),
         None => {
             let matching_albums = client.suggest_albums(album_name).await.unwrap_or_else(|e| {
                 log::warn!("suggest album search error: {e}");
@@ -58,6 +79,43 @@ pub async fn handle<C: HttpClient, I: Io>(
     }
 }
 
+/// Resolve and print NAS paths of `photos`
+pub async fn print_locations<C: ApiClient, I: Io>(
+    photos: Vec<Item>,
+    settings: (&UserSettings, &PathMapper),
+    output: OutputFormat,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+) -> Result<()> {
+    let records = locate(photos, settings, client).await?;
+    print_results(records, output, io)
+}
+
+/// Resolve NAS paths of `photos`
+pub async fn locate<C: ApiClient>(
+    photos: Vec<Item>,
+    (user_settings, path_mapper): (&UserSettings, &PathMapper),
+    client: &SessionClient<'_, C>,
+) -> Result<Vec<ListRecord>> {
+    if photos.is_empty() {
+        return Ok(vec![]);
+    }
+    let folder_ids: HashSet<u32> = photos.iter().map(|p| p.folder_id).collect();
+    let folders_future = get_folder_results(folder_ids, user_settings, client);
+
+    let owner_ids: HashSet<u32> = photos.iter().map(|p| p.owner_user_id).collect();
+    let users = client.get_users(&owner_ids).await?;
+    let user_map: HashMap<u32, UserInfo> = users.into_iter().map(|u| (u.id, u)).collect();
+
+    let folder_results = folders_future.await;
+    let photo_to_folder_result_map = map_photo_to_folder_result(photos, &folder_results);
+    Ok(to_records(
+        photo_to_folder_result_map,
+        user_map,
+        path_mapper,
+    ))
+}
+
 async fn get_folder_results<C: ApiClient>(
     folder_ids: HashSet<u32>,
     UserSettings {
@@ -116,51 +174,141 @@ fn map_photo_to_folder_result(
         .collect()
 }
 
-fn print_results<I: Io>(
+/// Location of an album item
+#[derive(Debug, Serialize)]
+pub struct ListRecord {
+    pub item_id: u32,
+    pub filename: String,
+    /// Resolved file-system path on NAS
+    pub path: Option<String>,
+    /// `None` for items in Shared Space
+    pub owner: Option<String>,
+    pub space: &'static str,
+    pub folder_id: u32,
+    pub error: Option<&'static str>,
+    pub error_message: Option<String>,
+}
+
+impl Record for ListRecord {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "item_id",
+            "filename",
+            "path",
+            "owner",
+            "space",
+            "folder_id",
+            "error",
+            "error_message",
+        ]
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.item_id.to_string(),
+            self.filename.clone(),
+            self.path.clone().unwrap_or_default(),
+            self.owner.clone().unwrap_or_default(),
+            self.space.to_string(),
+            self.folder_id.to_string(),
+            self.error.unwrap_or_default().to_string(),
+            self.error_message.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+fn to_records(
     photo_to_folder_result_map: HashMap<Item, &Result<Folder>>,
     user_map: HashMap<u32, UserInfo>,
-    io: &mut I,
-) -> Result<()> {
-    for (
-        Item {
-            filename,
-            owner_user_id,
-            ..
-        },
-        folder_result,
-    ) in photo_to_folder_result_map
-    {
-        const SHARED_SPACE: &str = "Shared Space";
-        let mut owner = user_map
-            .get(&owner_user_id)
-            .expect("user should be fetched")
-            .name
-            .as_str();
-        /* Photos in Shared Space have owner name set to "/volume1/photo" (or similar) */
-        if owner.starts_with("/volume") && owner.ends_with("/photo") {
-            owner = SHARED_SPACE;
-        }
-        match folder_result {
-            Ok(folder) => {
-                /* The following assumes standard locations, not sure if it's possible to have the
-                 * service folder links in different locations on DSM 7. */
-                let prefix = match owner {
-                    SHARED_SPACE => "/var/services/photo".to_string(),
-                    _ => format!("/var/services/homes/{owner}/Photos"),
+    path_mapper: &PathMapper,
+) -> Vec<ListRecord> {
+    photo_to_folder_result_map
+        .into_iter()
+        .map(
+            |(
+                Item {
+                    id,
+                    filename,
+                    owner_user_id,
+                    folder_id,
+                    ..
+                },
+                folder_result,
+            )| {
+                let owner = user_map
+                    .get(&owner_user_id)
+                    .expect("user should be fetched")
+                    .name
+                    .as_str();
+                let is_shared_space = is_shared_space_owner(owner);
+                let space = match is_shared_space {
+                    true => Space::Shared,
+                    false => Space::Personal,
                 };
-                let sub_folder = folder.name.trim_end_matches('/');
-                writeln!(io.stdout(), "{prefix}{sub_folder}/{filename}")?;
-            }
-            Err(e) => {
-                let e_str = match e.downcast_ref::<DsmError>() {
-                    Some(DsmError::Photo(PhotoError::NoAccessOrNotFound)) => {
-                        format!("no access (owned by {owner})")
+                let (path, error, error_message) = match folder_result {
+                    Ok(folder) => {
+                        let path = path_mapper.resolve((space, owner), &folder.name, &filename);
+                        (Some(path), None, None)
                     }
-                    _ => e.to_string(),
+                    Err(e) => match e.downcast_ref::<DsmError>() {
+                        Some(DsmError::Photo(PhotoError::NoAccessOrNotFound)) => {
+                            let owner = if is_shared_space {
+                                "Shared Space"
+                            } else {
+                                owner
+                            };
+                            (
+                                None,
+                                Some("no_access"),
+                                Some(format!("no access (owned by {owner})")),
+                            )
+                        }
+                        _ => (None, Some("other"), Some(e.to_string())),
+                    },
                 };
-                writeln!(io.stdout(), "Error: {e_str} '{filename}'")?;
-            }
-        };
+                ListRecord {
+                    item_id: id,
+                    filename,
+                    path,
+                    owner: (!is_shared_space).then(|| owner.to_string()),
+                    space: if is_shared_space {
+                        "shared"
+                    } else {
+                        "personal"
+                    },
+                    folder_id,
+                    error,
+                    error_message,
+                }
+            },
+        )
+        .collect()
+}
+
+/// Photos in Shared Space have owner name set to "/volume1/photo" (or similar)
+pub fn is_shared_space_owner(owner: &str) -> bool {
+    owner.starts_with("/volume") && owner.ends_with("/photo")
+}
+
+fn print_results<I: Io>(records: Vec<ListRecord>, output: OutputFormat, io: &mut I) -> Result<()> {
+    if output != OutputFormat::Text {
+        return write_records(&records, output, io.stdout());
+    }
+    for record in records {
+        match record {
+            ListRecord {
+                path: Some(path), ..
+            } => writeln!(io.stdout(), "{path}")?,
+            ListRecord {
+                filename,
+                error_message,
+                ..
+            } => writeln!(
+                io.stdout(),
+                "Error: {} '{filename}'",
+                error_message.unwrap_or_default()
+            )?,
+        }
     }
     Ok(())
 }
@@ -0,0 +1,256 @@
+//! Rewriting of NAS paths printed by list command
+
+use crate::commands::api_client::Space;
+use anyhow::{Result, anyhow, bail};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Rule replacing the location of Personal or Shared Space in printed paths, in the form of
+/// `SPACE[:USER]=PREFIX`, e.g. `shared=\\nas\photo` or `personal:alice=/mnt/nas/alice/Photos`.
+/// `{user}` in `PREFIX` is replaced with owner name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMapping {
+    space: Space,
+    /// Only applicable to Personal Space
+    user: Option<String>,
+    prefix: String,
+}
+
+impl FromStr for PathMapping {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (selector, prefix) = value
+            .split_once('=')
+            .ok_or(anyhow!("expected SPACE[:USER]=PREFIX"))?;
+        let (space, user) = match selector.split_once(':') {
+            Some((space, user)) => (space, Some(user)),
+            None => (selector, None),
+        };
+        let space = match space.trim() {
+            "personal" => Space::Personal,
+            "shared" => Space::Shared,
+            other => bail!("unknown space '{other}', expected 'personal' or 'shared'"),
+        };
+        let user = match (space, user.map(str::trim)) {
+            (_, None) => None,
+            (_, Some("")) => bail!("user name should not be empty"),
+            (Space::Shared, Some(_)) => bail!("user can only be specified for personal space"),
+            (Space::Personal, Some(user)) => Some(user.to_string()),
+        };
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            bail!("prefix should not be empty")
+        }
+        Ok(PathMapping {
+            space,
+            user,
+            prefix: prefix.to_string(),
+        })
+    }
+}
+
+impl Display for PathMapping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let space = match self.space {
+            Space::Personal => "personal",
+            Space::Shared => "shared",
+        };
+        match &self.user {
+            Some(user) => write!(f, "{space}:{user}={}", self.prefix),
+            None => write!(f, "{space}={}", self.prefix),
+        }
+    }
+}
+
+/// Resolves file-system paths of photos on NAS
+#[derive(Debug, Default)]
+pub struct PathMapper {
+    mappings: Vec<PathMapping>,
+    /// Use real `/volumeN` locations instead of `/var/services` links
+    volume_paths: bool,
+    /// Location of the homes shared folder, e.g. `/volume2/homes`
+    homes_path: Option<String>,
+}
+
+impl PathMapper {
+    /// Mappings taking precedence should come first
+    pub fn new(
+        mappings: Vec<PathMapping>,
+        (volume_paths, homes_path): (bool, Option<String>),
+    ) -> Self {
+        PathMapper {
+            mappings,
+            volume_paths,
+            homes_path,
+        }
+    }
+
+    /// Real location of the homes shared folder. The API only returns the location of the photo
+    /// shared folder (as owner name of Shared Space items), which may be on a different volume,
+    /// so the first volume is assumed unless configured.
+    fn homes_path(&self) -> &str {
+        self.homes_path
+            .as_deref()
+            .unwrap_or("/volume1/homes")
+            .trim_end_matches('/')
+    }
+
+    /// * `owner` - owner name of a photo in Personal Space, or location of the `photo` shared
+    ///   folder (e.g. `/volume1/photo`) for Shared Space
+    /// * `sub_folder` - folder path in Synology Photos (starts with `/`)
+    pub fn resolve(
+        &self,
+        (space, owner): (Space, &str),
+        sub_folder: &str,
+        filename: &str,
+    ) -> String {
+        let user_mapping = self
+            .mappings
+            .iter()
+            .find(|m| m.space == space && m.user.as_deref() == Some(owner));
+        let space_mapping = || {
+            self.mappings
+                .iter()
+                .find(|m| m.space == space && m.user.is_none())
+        };
+        let sub_folder = sub_folder.trim_end_matches('/');
+        match user_mapping.or_else(space_mapping) {
+            Some(mapping) => {
+                let prefix = mapping.prefix.replace("{user}", owner);
+                let prefix = prefix.trim_end_matches(['/', '\\']);
+                /* Use Windows separators for UNC paths and drive letters */
+                if prefix.contains('\\') && !prefix.contains('/') {
+                    format!("{prefix}{}\\{filename}", sub_folder.replace('/', "\\"))
+                } else {
+                    format!("{prefix}{sub_folder}/{filename}")
+                }
+            }
+            None => {
+                /* The following assumes standard locations, not sure if it's possible to have the
+                 * service folder links in different locations on DSM 7. */
+                let prefix = match (space, self.volume_paths) {
+                    (Space::Shared, false) => "/var/services/photo".to_string(),
+                    (Space::Shared, true) => owner.to_string(),
+                    (Space::Personal, false) => format!("/var/services/homes/{owner}/Photos"),
+                    (Space::Personal, true) => format!("{}/{owner}/Photos", self.homes_path()),
+                };
+                format!("{prefix}{sub_folder}/{filename}")
+            }
+        }
+    }
+
+    /// Reverse of [Self::resolve] for Shared Space and Personal Space of `user`. Returns space,
+    /// folder path in Synology Photos and file name, or `None` when `path` is not located in
+    /// either of them.
+    pub fn parse(&self, path: &str, user: &str) -> Option<(Space, String, String)> {
+        let mut prefixes: Vec<(Space, String)> = self
+            .mappings
+            .iter()
+            .filter(|m| m.user.as_deref() == Some(user))
+            .chain(self.mappings.iter().filter(|m| m.user.is_none()))
+            .map(|m| (m.space, m.prefix.replace("{user}", user)))
+            .collect();
+        prefixes.push((
+            Space::Personal,
+            format!("/var/services/homes/{user}/Photos"),
+        ));
+        prefixes.push((Space::Shared, "/var/services/photo".to_string()));
+        if self.homes_path.is_some() {
+            prefixes.push((
+                Space::Personal,
+                format!("{}/{user}/Photos", self.homes_path()),
+            ));
+        }
+        if let Some(volume) = path.strip_prefix('/').and_then(|p| p.split('/').next()) {
+            if volume.starts_with("volume") {
+                prefixes.push((Space::Personal, format!("/{volume}/homes/{user}/Photos")));
+                prefixes.push((Space::Shared, format!("/{volume}/photo")));
+            }
+        }
+        prefixes.into_iter().find_map(|(space, prefix)| {
+            let prefix = prefix.trim_end_matches(['/', '\\']);
+            let rest = path.strip_prefix(prefix)?;
+            let rest = if prefix.contains('\\') && !prefix.contains('/') {
+                rest.replace('\\', "/")
+            } else {
+                rest.to_string()
+            };
+            let (sub_folder, filename) = rest.rsplit_once('/')?;
+            if !rest.starts_with('/') || filename.is_empty() {
+                return None;
+            }
+            let sub_folder = if sub_folder.is_empty() {
+                "/"
+            } else {
+                sub_folder
+            };
+            Some((space, sub_folder.to_string(), filename.to_string()))
+        })
+    }
+}
+
+#[test]
+fn resolve_applies_most_specific_mapping() {
+    let mapper = PathMapper::new(
+        vec![
+            "personal:bob=/mnt/bob".parse().unwrap(),
+            "personal=/mnt/nas/homes/{user}/Photos".parse().unwrap(),
+            r"shared=\\nas\photo".parse().unwrap(),
+        ],
+        (false, None),
+    );
+    assert_eq!(
+        mapper.resolve((Space::Personal, "bob"), "/2022/", "a.jpg"),
+        "/mnt/bob/2022/a.jpg"
+    );
+    assert_eq!(
+        mapper.resolve((Space::Personal, "alice"), "/2022", "a.jpg"),
+        "/mnt/nas/homes/alice/Photos/2022/a.jpg"
+    );
+    assert_eq!(
+        mapper.resolve((Space::Shared, "/volume2/photo"), "/2022/11", "a.jpg"),
+        r"\\nas\photo\2022\11\a.jpg"
+    );
+    assert_eq!(
+        PathMapper::new(vec![], (true, None)).resolve(
+            (Space::Shared, "/volume2/photo"),
+            "/",
+            "a.jpg"
+        ),
+        "/volume2/photo/a.jpg"
+    );
+    assert_eq!(
+        PathMapper::new(vec![], (true, None)).resolve((Space::Personal, "bob"), "/", "a.jpg"),
+        "/volume1/homes/bob/Photos/a.jpg"
+    );
+    assert_eq!(
+        PathMapper::new(vec![], (true, Some("/volume3/homes/".to_string()))).resolve(
+            (Space::Personal, "bob"),
+            "/2022",
+            "a.jpg"
+        ),
+        "/volume3/homes/bob/Photos/2022/a.jpg"
+    );
+}
+
+#[test]
+fn parse_reverses_resolve() {
+    let mapper = PathMapper::new(vec![r"shared=\\nas\photo".parse().unwrap()], (false, None));
+    assert_eq!(
+        mapper.parse("/var/services/homes/alice/Photos/2022/a.jpg", "alice"),
+        Some((Space::Personal, "/2022".to_string(), "a.jpg".to_string()))
+    );
+    assert_eq!(
+        mapper.parse("/volume2/homes/alice/Photos/a.jpg", "alice"),
+        Some((Space::Personal, "/".to_string(), "a.jpg".to_string()))
+    );
+    assert_eq!(
+        mapper.parse(r"\\nas\photo\2022\11\a.jpg", "alice"),
+        Some((Space::Shared, "/2022/11".to_string(), "a.jpg".to_string()))
+    );
+    assert_eq!(
+        mapper.parse("/var/services/homes/bob/Photos/a.jpg", "alice"),
+        None
+    );
+}
@@ -1,14 +1,55 @@
 use anyhow::{Result, bail};
+use serde::Serialize;
 use std::io::Write;
 use syno_api::foto::browse::album::dto::Album;
 
-use crate::commands::api_client::{ApiClient, SessionClient};
+use crate::commands::api_client::{PAGE_SIZE, SessionClient};
 use crate::conf::Conf;
 use crate::http::HttpClient;
 use crate::io::Io;
+use crate::output::{OutputFormat, Record, write_records};
+
+#[derive(Debug, Serialize)]
+struct AlbumRecord {
+    id: u32,
+    name: String,
+    /// "owned" or "shared"
+    kind: &'static str,
+    item_count: u32,
+    owner_user_id: u32,
+}
+
+impl Record for AlbumRecord {
+    fn csv_header() -> &'static [&'static str] {
+        &["id", "name", "kind", "item_count", "owner_user_id"]
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.kind.to_string(),
+            self.item_count.to_string(),
+            self.owner_user_id.to_string(),
+        ]
+    }
+}
+
+impl AlbumRecord {
+    fn new(album: Album, kind: &'static str) -> Self {
+        AlbumRecord {
+            id: album.id,
+            name: album.name,
+            kind,
+            item_count: album.item_count,
+            owner_user_id: album.owner_user_id,
+        }
+    }
+}
 
 pub async fn handle<C: HttpClient, I: Io>(
     (show_owned, show_shared): (bool, bool),
+    output: OutputFormat,
     conf: &Conf,
     client: &C,
     io: &mut I,
@@ -18,38 +59,32 @@ pub async fn handle<C: HttpClient, I: Io>(
     }
     let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
     let show_all_by_default = !show_owned && !show_shared;
+    let mut records = vec![];
     if show_owned || show_all_by_default {
-        let owned_albums_count = client.count_owned_albums().await?;
-        let owned_albums_list = client.list_owned_albums(owned_albums_count).await?;
-        for owned_album in owned_albums_list {
-            writeln!(io.stdout(), "{}", owned_album.name)?;
-        }
+        let owned_albums_list = client
+            .all_pages(PAGE_SIZE, |c, page| c.list_owned_albums(page))
+            .await?;
+        records.extend(
+            owned_albums_list
+                .into_iter()
+                .map(|a| AlbumRecord::new(a, "owned")),
+        );
     }
     if show_shared || show_all_by_default {
-        let shared_albums_list = list_shared_albums(&client).await?;
-        for shared_album in shared_albums_list {
-            writeln!(io.stdout(), "{}", shared_album.name)?;
-        }
-    }
-    Ok(())
-}
-
-/// Search among shared albums. There is no known API method to detect the number of
-/// shared albums, we need to query the list in chunks until we find it or there are no
-/// more albums returned.
-async fn list_shared_albums<C: ApiClient>(client: &SessionClient<'_, C>) -> Result<Vec<Album>> {
-    let mut offset = 0;
-    const LIMIT: u32 = 50;
-    let mut shared_albums = vec![];
-    loop {
-        let mut shared_album_list = client
-            .list_shared_with_me_albums(offset, offset + LIMIT)
+        let shared_albums_list = client
+            .all_pages(PAGE_SIZE, |c, page| c.list_shared_with_me_albums(page))
             .await?;
-        shared_albums.append(&mut shared_album_list);
-        if shared_album_list.len() < LIMIT as usize {
-            break;
-        }
-        offset += LIMIT;
+        records.extend(
+            shared_albums_list
+                .into_iter()
+                .map(|a| AlbumRecord::new(a, "shared")),
+        );
+    }
+    if output != OutputFormat::Text {
+        return write_records(&records, output, io.stdout());
     }
-    Ok(shared_albums)
+    for record in records {
+        writeln!(io.stdout(), "{}", record.name)?;
+    }
+    Ok(())
 }
@@ -0,0 +1,296 @@
+use crate::commands::api_client::{SessionClient, Space};
+use crate::commands::list::is_shared_space_owner;
+use crate::commands::{album_not_found, find_album};
+use crate::conf::Conf;
+use crate::http::HttpClient;
+use crate::io::Io;
+use crate::output::{OutputFormat, Record, write_records};
+use anyhow::{Context, Result, bail};
+use chrono::DateTime;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use syno_api::foto::browse::item::dto::{Additional, Item, Resolution};
+use syno_api::foto::user_info::dto::UserInfo;
+
+/// Metadata of an album item
+#[derive(Debug, Serialize)]
+struct ItemRecord {
+    item_id: u32,
+    filename: String,
+    /// Time the photo was taken, as shown in Synology Photos (no time zone)
+    taken: String,
+    /// In bytes
+    filesize: u32,
+    width: Option<u32>,
+    height: Option<u32>,
+    /// "photo", "video" or "live"
+    r#type: String,
+    /// `None` for items in Shared Space
+    owner: Option<String>,
+    space: &'static str,
+}
+
+impl Record for ItemRecord {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "item_id", "filename", "taken", "filesize", "width", "height", "type", "owner", "space",
+        ]
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.item_id.to_string(),
+            self.filename.clone(),
+            self.taken.clone(),
+            self.filesize.to_string(),
+            self.width.map(|w| w.to_string()).unwrap_or_default(),
+            self.height.map(|h| h.to_string()).unwrap_or_default(),
+            self.r#type.clone(),
+            self.owner.clone().unwrap_or_default(),
+            self.space.to_string(),
+        ]
+    }
+}
+
+impl ItemRecord {
+    fn new(item: Item, user_map: &HashMap<u32, UserInfo>) -> Self {
+        let owner = user_map
+            .get(&item.owner_user_id)
+            .map(|u| u.name.as_str())
+            .unwrap_or_default();
+        let space = match is_shared_space_owner(owner) {
+            true => Space::Shared,
+            false => Space::Personal,
+        };
+        let resolution = match item.additional {
+            Some(Additional {
+                resolution: Some(Resolution { width, height }),
+                ..
+            }) => Some((width, height)),
+            _ => None,
+        };
+        ItemRecord {
+            item_id: item.id,
+            filename: item.filename,
+            taken: format_time(item.time),
+            filesize: item.filesize,
+            width: resolution.map(|(w, _)| w),
+            height: resolution.map(|(_, h)| h),
+            r#type: item.r#type,
+            owner: (space == Space::Personal).then(|| owner.to_string()),
+            space: match space {
+                Space::Personal => "personal",
+                Space::Shared => "shared",
+            },
+        }
+    }
+}
+
+/// Format time the photo was taken. Synology Photos stores local time of the photo as if it was
+/// UTC, so no time zone is shown.
+pub fn format_time(time: u64) -> String {
+    DateTime::from_timestamp(time as i64, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+pub async fn handle<C: HttpClient, I: Io>(
+    album_name: &str,
+    output: OutputFormat,
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+) -> Result<()> {
+    if !conf.is_logged_in() {
+        bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
+    }
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
+    let user_settings = client.get_user_settings().await?;
+    let team_space_settings = client.get_team_space_settings().await?;
+
+    let album = find_album(album_name, &user_settings, &team_space_settings, &client).await?;
+    match album {
+        Some(album) => {
+            let items = client
+                .list_all_items(&album)
+                .await
+                .with_context(|| "listing album contents failed")?;
+            if items.is_empty() {
+                return print_results(vec![], output, io);
+            }
+            let owner_ids: HashSet<u32> = items.iter().map(|i| i.owner_user_id).collect();
+            let users = client.get_users(&owner_ids).await?;
+            let user_map: HashMap<u32, UserInfo> = users.into_iter().map(|u| (u.id, u)).collect();
+            let records = items
+                .into_iter()
+                .map(|item| ItemRecord::new(item, &user_map))
+                .collect();
+            print_results(records, output, io)
+        }
+        None if output != OutputFormat::Text => bail!("album '{album_name}' not found"),
+        None => {
+            let matching_albums = client.suggest_albums(album_name).await.unwrap_or_else(|e| {
+                log::warn!("suggest album search error: {e}");
+                vec![]
+            });
+            album_not_found(album_name, matching_albums, io)
+        }
+    }
+}
+
+fn print_results<I: Io>(records: Vec<ItemRecord>, output: OutputFormat, io: &mut I) -> Result<()> {
+    if output != OutputFormat::Text {
+        return write_records(&records, output, io.stdout());
+    }
+    for record in records {
+        let dimensions = match (record.width, record.height) {
+            (Some(width), Some(height)) => format!("{width}x{height}"),
+            _ => "-".to_string(),
+        };
+        writeln!(
+            io.stdout(),
+            "{:>8}  {}  {:>10}  {:>11}  {:<5}  {:<12}  {}",
+            record.item_id,
+            record.taken,
+            record.filesize,
+            dimensions,
+            record.r#type,
+            record.owner.as_deref().unwrap_or("Shared Space"),
+            record.filename
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use serde_json::json;
+    use syno_api::foto;
+    use syno_api::foto::browse::album::dto::Album as AlbumDto;
+    use syno_api::foto::setting::team_space::dto::TeamSpaceSettings;
+    use syno_api::foto::setting::user::dto::UserSettings;
+
+    fn client() -> FakeHttpClient {
+        FakeHttpClient::new(|request| {
+            let first_page = request.param("offset").as_deref() == Some("0");
+            if request.is(foto::setting::user::API, "get") {
+                return FakeResponse::data(UserSettings::default());
+            }
+            if request.is(foto::setting::team_space::API, "get") {
+                return FakeResponse::data(TeamSpaceSettings::default());
+            }
+            if request.is(foto::user_info::API, "get") {
+                let user = |id, name: &str| UserInfo {
+                    id,
+                    name: name.to_string(),
+                    ..UserInfo::default()
+                };
+                return FakeResponse::data(
+                    json!({"list": [user(1, "alice"), user(2, "/volume1/photo")]}),
+                );
+            }
+            let list = match first_page {
+                true if request.is(foto::browse::album::API, "list") => json!([AlbumDto {
+                    id: 5,
+                    name: "Trips".to_string(),
+                    ..AlbumDto::default()
+                }]),
+                true if request.is(foto::browse::item::API, "list") => {
+                    assert_eq!(request.param("album_id").as_deref(), Some("5"));
+                    json!([
+                        Item {
+                            id: 10,
+                            filename: "a.jpg".to_string(),
+                            time: 1720000000,
+                            filesize: 2048,
+                            r#type: "photo".to_string(),
+                            owner_user_id: 1,
+                            additional: Some(Additional {
+                                resolution: Some(Resolution {
+                                    width: 4000,
+                                    height: 3000,
+                                }),
+                                ..Additional::default()
+                            }),
+                            ..Item::default()
+                        },
+                        Item {
+                            id: 11,
+                            filename: "b, c.mp4".to_string(),
+                            time: 1720000000,
+                            filesize: 4096,
+                            r#type: "video".to_string(),
+                            owner_user_id: 2,
+                            ..Item::default()
+                        }
+                    ])
+                }
+                _ => json!([]),
+            };
+            FakeResponse::data(json!({ "list": list }))
+        })
+    }
+
+    #[tokio::test]
+    async fn list_items_as_text() {
+        let mut io = FakeIo::new("");
+
+        handle(
+            "trips",
+            OutputFormat::Text,
+            &logged_in_conf(),
+            &client(),
+            &mut io,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            io.stdout_str(),
+            "      10  2024-07-03 09:46:40        2048    4000x3000  photo  alice         a.jpg\n      \
+                   11  2024-07-03 09:46:40        4096            -  video  Shared Space  b, c.mp4\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn list_items_as_csv() {
+        let mut io = FakeIo::new("");
+
+        handle(
+            "Trips",
+            OutputFormat::Csv,
+            &logged_in_conf(),
+            &client(),
+            &mut io,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            io.stdout_str(),
+            "item_id,filename,taken,filesize,width,height,type,owner,space\n\
+             10,a.jpg,2024-07-03 09:46:40,2048,4000,3000,photo,alice,personal\n\
+             11,\"b, c.mp4\",2024-07-03 09:46:40,4096,,,video,,shared\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn list_items_of_missing_album_fails_with_csv() {
+        let mut io = FakeIo::new("");
+
+        let error = handle(
+            "Nothing",
+            OutputFormat::Csv,
+            &logged_in_conf(),
+            &client(),
+            &mut io,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(error.to_string(), "album 'Nothing' not found");
+    }
+}
@@ -61,7 +61,7 @@ impl InputReader for Option<String> {
     }
 }
 
-#[derive(Debug, Display, Deserialize, Serialize)]
+#[derive(Debug, Clone, Display, Deserialize, Serialize)]
 pub struct DeviceId(String);
 
 impl DeviceId {
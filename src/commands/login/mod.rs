@@ -1,10 +1,11 @@
 use super::DsmError;
 use crate::{
     CookieClient,
-    conf::{Conf, Session},
+    conf::{Conf, Credentials, Session},
     fs::Fs,
     http::{CookieStore, HttpClient, Url},
     io::{Io, read_input},
+    secrets::SecretBackend,
 };
 use anyhow::{Result, anyhow, bail};
 use creds::{DeviceId, InputReader, UserCredentials};
@@ -14,19 +15,36 @@ use syno_api::auth::error::AuthError;
 
 mod api_client;
 pub mod creds;
+pub mod relogin;
 
 pub type LoginArgs = (Option<String>, Option<String>, bool);
 
+/// `(secret_store, save_password)` - secret store to switch to, and whether to store the password
+pub type SecretArgs = (Option<SecretBackend>, bool);
+
 pub async fn handle<C: HttpClient, S: CookieStore, I: Io, F: Fs>(
     dsm_url: Option<Url>,
-    (user, password, remember_dev): LoginArgs,
+    (mut user, mut password, remember_dev): LoginArgs,
+    (secret_store, save_password): SecretArgs,
     conf: &mut Conf,
     client: &CookieClient<C, S>,
     io: &mut I,
     fs: &F,
 ) -> Result<()> {
+    let previous_secret_store = conf.secret_store;
+    if let Some(secret_store) = secret_store {
+        conf.secret_store = secret_store;
+    }
+    if save_password && conf.secret_store == SecretBackend::Conf {
+        bail!("storing password requires a secret store, see '--secret-store' option");
+    }
+    if let (None, None, Some(credentials)) = (&user, &password, &conf.credentials) {
+        /* Stored credentials allow to sign in again without user interaction */
+        user = Some(credentials.user.clone());
+        password = Some(credentials.password.clone());
+    }
     let dsm_url = unwrap_or_read_dsm_url(dsm_url, conf, io)?;
-    let login_dto = login_flow(
+    let (login_dto, credentials) = login_flow(
         &dsm_url,
         (user, password, remember_dev),
         conf,
@@ -45,7 +63,21 @@ pub async fn handle<C: HttpClient, S: CookieStore, I: Io, F: Fs>(
     if remember_dev {
         conf.set_device_id(DeviceId::new(login_dto.did)?);
     }
-    conf.try_save(fs)
+    if save_password {
+        conf.credentials = Some(credentials);
+    }
+    conf.try_save(fs)?;
+    if conf.secret_store != previous_secret_store {
+        /* Secrets have been moved to the new store */
+        let previous = Conf {
+            secret_store: previous_secret_store,
+            ..conf.clone()
+        };
+        if let Err(error) = previous.delete_secrets(fs) {
+            log::warn!("deleting secrets from {previous_secret_store} failed: {error}");
+        }
+    }
+    Ok(())
 }
 
 /// Unwrap `url` or try to read it from `conf`, and if that also fails, from stdin.
@@ -72,14 +104,14 @@ fn unwrap_or_read_dsm_url<I: Io>(url: Option<Url>, conf: &Conf, io: &mut I) -> R
 }
 
 /// Fetch sid and did from SYNO.Auth API using account and password, then (if MFA is
-/// enabled) read OTP code and try again.
+/// enabled) read OTP code and try again. Returns the used credentials along with the result.
 async fn login_flow<I: Io, C: HttpClient>(
     dsm_url: &Url,
     (mut user, mut password, remember_dev): LoginArgs,
     conf: &Conf,
     io: &mut I,
     client: &C,
-) -> Result<Login> {
+) -> Result<(Login, Credentials)> {
     let mut user_credentials = UserCredentials::new(
         user.unwrap_or_read_stdin(io, "DSM user")?,
         password.unwrap_or_read_password(io)?,
@@ -100,5 +132,68 @@ async fn login_flow<I: Io, C: HttpClient>(
         }
         Ok(dto) => dto,
     };
-    Ok(login_dto)
+    let UserCredentials {
+        account, passwd, ..
+    } = user_credentials;
+    Ok((
+        login_dto,
+        Credentials {
+            user: account,
+            password: passwd,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{DSM_URL, FakeFs, FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use reqwest::cookie::Jar;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn switching_secret_store_deletes_secrets_from_previous_one() {
+        let fs = FakeFs::new();
+        let mut conf = Conf {
+            secret_store: SecretBackend::File,
+            ..logged_in_conf()
+        };
+        conf.try_save(&fs).unwrap();
+        let client = FakeHttpClient::new(|request| {
+            assert!(request.is(syno_api::auth::API, "login"));
+            FakeResponse::data(Login::default())
+        });
+        /* The fake client does not set cookies, so the new session cookie is there upfront */
+        let cookie_store = Arc::new(Jar::default());
+        cookie_store.add_cookie_str("id=new", &Url::parse(DSM_URL).unwrap());
+        let client = CookieClient {
+            client,
+            cookie_store,
+        };
+        let mut io = FakeIo::new("");
+
+        handle(
+            None,
+            (
+                Some("alice".to_string()),
+                Some("password".to_string()),
+                false,
+            ),
+            (Some(SecretBackend::Conf), false),
+            &mut conf,
+            &client,
+            &mut io,
+            &fs,
+        )
+        .await
+        .unwrap();
+
+        let secrets = fs
+            .read_to_string("/home/.syno-photos-util-secrets")
+            .unwrap();
+        assert!(!secrets.contains("default/"));
+        let saved = Conf::load(&fs, None).unwrap();
+        assert_eq!(saved.secret_store, SecretBackend::Conf);
+        assert_eq!(saved.session.unwrap().cookie, "id=new");
+    }
 }
@@ -0,0 +1,246 @@
+//! Automatic sign-in when DSM session expires, e.g. in scheduled jobs
+
+use super::DsmError;
+use super::api_client;
+use super::creds::{DeviceId, UserCredentials};
+use crate::CookieClient;
+use crate::conf::{Conf, Credentials, Session};
+use crate::fs::Fs;
+use crate::http::{CookieStore, HttpClient, Url};
+use anyhow::{Result, anyhow, bail};
+use reqwest::IntoUrl;
+use serde::Serialize;
+use std::path::Path;
+use syno_api::auth::error::AuthError;
+use tokio::sync::Mutex;
+
+const USER_VAR: &str = "SYNO_PHOTOS_UTIL_USER";
+const PASSWORD_VAR: &str = "SYNO_PHOTOS_UTIL_PASSWORD";
+
+/// Wraps `HttpClient`, signing in again with `credentials` when the session expires. The new
+/// session is saved in conf file.
+pub struct ReloginClient<'a, C: HttpClient, S: CookieStore, F: Fs> {
+    client: &'a CookieClient<C, S>,
+    /// `None` when automatic sign-in is not enabled
+    relogin: Option<(Url, Credentials, Option<DeviceId>)>,
+    profile: String,
+    fs: &'a F,
+    /// Whether signing in again succeeded, once attempted; only one attempt is made per run
+    renewed: Mutex<Option<bool>>,
+}
+
+impl<'a, C: HttpClient, S: CookieStore, F: Fs> ReloginClient<'a, C, S, F> {
+    /// * `credentials` - when `None`, the client does not sign in again
+    pub fn new(
+        conf: &Conf,
+        credentials: Option<Credentials>,
+        client: &'a CookieClient<C, S>,
+        fs: &'a F,
+    ) -> Self {
+        let relogin = match (&conf.session, credentials) {
+            (Some(session), Some(credentials)) => Some((
+                session.url.clone(),
+                credentials,
+                conf.get_device_id(&session.url).cloned(),
+            )),
+            _ => None,
+        };
+        ReloginClient {
+            client,
+            relogin,
+            profile: conf.profile.clone(),
+            fs,
+            renewed: Mutex::new(None),
+        }
+    }
+}
+
+impl<C: HttpClient, S: CookieStore, F: Fs> HttpClient for ReloginClient<'_, C, S, F> {
+    type Response = C::Response;
+
+    async fn get<U: IntoUrl>(&self, url: U) -> Result<Self::Response> {
+        self.client.client.get(url).await
+    }
+
+    async fn post<U: IntoUrl, T: Serialize>(&self, url: U, form: &T) -> Result<Self::Response> {
+        self.client.client.post(url, form).await
+    }
+
+    async fn relogin(&self) -> Result<bool> {
+        let Some(relogin) = &self.relogin else {
+            return Ok(false);
+        };
+        /* Concurrent requests may fail at the same time, only the first one signs in and the
+         * others get its outcome */
+        let mut renewed = self.renewed.lock().await;
+        if let Some(renewed) = *renewed {
+            return Ok(renewed);
+        }
+        let result = self.sign_in(relogin).await;
+        *renewed = Some(result.is_ok());
+        result.map(|()| true)
+    }
+}
+
+impl<C: HttpClient, S: CookieStore, F: Fs> ReloginClient<'_, C, S, F> {
+    async fn sign_in(
+        &self,
+        (url, credentials, device_id): &(Url, Credentials, Option<DeviceId>),
+    ) -> Result<()> {
+        log::info!("session expired, signing in to {url} again");
+        let user_credentials = UserCredentials::new(
+            credentials.user.clone(),
+            credentials.password.clone(),
+            device_id.as_ref(),
+        );
+        if let Err(error) =
+            api_client::login(&user_credentials, false, url, &self.client.client).await
+        {
+            return match error.downcast::<DsmError>()? {
+                DsmError::Auth(AuthError::MfaCodeRequired)
+                | DsmError::Auth(AuthError::EnforceAuthWithMfa) => Err(anyhow!(
+                    "signing in again requires OTP code; use 'login --remember' to skip OTP \
+                     verification"
+                )),
+                other => Err(anyhow!("signing in again failed: {other}")),
+            };
+        }
+        let session_cookie = self
+            .client
+            .cookie_store
+            .cookies(url)
+            .expect("login response should contain session cookie");
+        let mut conf = Conf::load(self.fs, Some(self.profile.as_str()))?;
+        conf.session = Some(Session {
+            url: url.clone(),
+            cookie: session_cookie.to_str()?.to_string(),
+        });
+        conf.try_save(self.fs)
+    }
+}
+
+/// Find credentials for automatic sign-in in environment variables, `credentials_file` (user
+/// name in the first line, password in the second), or the ones stored in secret store, in this
+/// order
+pub fn find_credentials<F: Fs>(
+    credentials_file: Option<&Path>,
+    conf: &Conf,
+    fs: &F,
+) -> Result<Option<Credentials>> {
+    if let (Ok(user), Ok(password)) = (std::env::var(USER_VAR), std::env::var(PASSWORD_VAR)) {
+        return Ok(Some(Credentials { user, password }));
+    }
+    if let Some(path) = credentials_file {
+        let data = fs
+            .read_to_string(path)
+            .map_err(|e| anyhow!("unable to read {}: {e}", path.display()))?;
+        let mut lines = data.lines();
+        let (Some(user), Some(password)) = (lines.next(), lines.next()) else {
+            bail!(
+                "{} should contain user name and password in separate lines",
+                path.display()
+            )
+        };
+        return Ok(Some(Credentials {
+            user: user.trim().to_string(),
+            password: password.to_string(),
+        }));
+    }
+    Ok(conf.credentials.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::api_client::{SessionClient, Space};
+    use crate::commands::error::is_session_error;
+    use crate::test::{DSM_URL, FakeFs, FakeHttpClient, FakeResponse, logged_in_conf};
+    use reqwest::cookie::Jar;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use syno_api::auth::dto::Login;
+
+    fn credentials() -> Option<Credentials> {
+        Some(Credentials {
+            user: "alice".to_string(),
+            password: "password".to_string(),
+        })
+    }
+
+    /// Client counting sign-ins, which succeed unless `login_error` is given
+    fn cookie_client(
+        login_error: Option<u16>,
+    ) -> (CookieClient<FakeHttpClient, Jar>, Arc<AtomicUsize>) {
+        let sign_ins = Arc::new(AtomicUsize::new(0));
+        let counter = sign_ins.clone();
+        let client = FakeHttpClient::new(move |request| {
+            if request.is(syno_api::auth::API, "login") {
+                assert_eq!(request.param("account").as_deref(), Some("alice"));
+                counter.fetch_add(1, Ordering::SeqCst);
+                return match login_error {
+                    Some(code) => FakeResponse::error(code),
+                    None => FakeResponse::data(Login::default()),
+                };
+            }
+            assert!(request.is("SYNO.Foto.Download", "download"));
+            match (counter.load(Ordering::SeqCst), login_error) {
+                (1, None) => FakeResponse::body("content"),
+                _ => FakeResponse::error(119), // invalid session
+            }
+        });
+        /* The fake client does not set cookies, so the renewed session cookie is there upfront */
+        let cookie_store = Arc::new(Jar::default());
+        cookie_store.add_cookie_str("id=renewed", &Url::parse(DSM_URL).unwrap());
+        (
+            CookieClient {
+                client,
+                cookie_store,
+            },
+            sign_ins,
+        )
+    }
+
+    #[tokio::test]
+    async fn download_is_retried_after_signing_in_again() {
+        let (client, sign_ins) = cookie_client(None);
+        let fs = FakeFs::new();
+        let conf = logged_in_conf();
+        let relogin_client = ReloginClient::new(&conf, credentials(), &client, &fs);
+        let session_client = SessionClient::new(conf.session.as_ref().unwrap(), &relogin_client);
+
+        let mut download = session_client
+            .download_item(1, Space::Personal)
+            .await
+            .unwrap();
+
+        assert_eq!(download.chunk().await.unwrap().unwrap(), b"content");
+        assert_eq!(sign_ins.load(Ordering::SeqCst), 1);
+        let saved = Conf::load(&fs, None).unwrap();
+        assert_eq!(saved.session.unwrap().cookie, "id=renewed");
+    }
+
+    #[tokio::test]
+    async fn failed_sign_in_is_reported_to_later_requests() {
+        let (client, sign_ins) = cookie_client(Some(400)); // incorrect password
+        let fs = FakeFs::new();
+        let conf = logged_in_conf();
+        let relogin_client = ReloginClient::new(&conf, credentials(), &client, &fs);
+        let session_client = SessionClient::new(conf.session.as_ref().unwrap(), &relogin_client);
+
+        let error = session_client
+            .download_item(1, Space::Personal)
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("signing in again failed"));
+        /* No second attempt, the original error is returned */
+        let error = session_client
+            .download_item(2, Space::Personal)
+            .await
+            .err()
+            .unwrap();
+        assert!(is_session_error(&error));
+        assert!(!relogin_client.relogin().await.unwrap());
+        assert_eq!(sign_ins.load(Ordering::SeqCst), 1);
+    }
+}
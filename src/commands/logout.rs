@@ -1,14 +1,102 @@
+use crate::commands::api_client::{ApiClient, ApiParams};
 use crate::conf::Conf;
 use crate::fs::Fs;
+use crate::http::{HttpClient, Url};
 use anyhow::Result;
+use serde::de::IgnoredAny;
+use syno_api::auth;
 
-pub fn handle<F: Fs>(conf: Conf, forget_dev: bool, fs: &F) -> Result<()> {
+/// `(forget, local_only)` flags
+pub type LogoutArgs = (bool, bool);
+
+pub async fn handle<C: HttpClient, F: Fs>(
+    conf: Conf,
+    (forget_dev, local_only): LogoutArgs,
+    client: &C,
+    fs: &F,
+) -> Result<()> {
+    if let (Some(session), false) = (&conf.session, local_only) {
+        /* The session is removed locally even when DSM cannot be reached */
+        if let Err(error) = logout(&session.url, client).await {
+            log::warn!("signing out of DSM failed: {error}; removing session locally");
+        }
+    }
     let mut new_conf = Conf {
         session: None,
         ..conf
     };
     if forget_dev {
-        new_conf.device_ids.clear()
+        new_conf.device_ids.clear();
+        new_conf.credentials = None;
     }
     new_conf.try_save(fs)
 }
+
+/// Invalidate session on DSM. Session cookie is sent by the client.
+async fn logout<C: ApiClient>(dsm_url: &Url, client: &C) -> Result<()> {
+    let _: IgnoredAny = client
+        .get(dsm_url.clone(), ApiParams::new(auth::API, "logout", 6), &[])
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::login::creds::DeviceId;
+    use crate::test::{DSM_URL, FakeFs, FakeHttpClient, FakeResponse, logged_in_conf};
+    use std::sync::{Arc, Mutex};
+
+    /// Signed in conf saved in `fs`, and a client recording logout requests
+    fn signed_in(fs: &FakeFs) -> (Conf, FakeHttpClient, Arc<Mutex<u32>>) {
+        let mut conf = logged_in_conf();
+        conf.set_device_id(DeviceId::new("device-1".to_string()).unwrap());
+        conf.try_save(fs).unwrap();
+        let logouts = Arc::new(Mutex::new(0));
+        let requests = logouts.clone();
+        let client = FakeHttpClient::new(move |request| {
+            assert!(request.is(auth::API, "logout"));
+            *requests.lock().unwrap() += 1;
+            FakeResponse::data(())
+        });
+        (conf, client, logouts)
+    }
+
+    #[tokio::test]
+    async fn logout_signs_out_on_dsm_and_keeps_device_id() {
+        let fs = FakeFs::new();
+        let (conf, client, logouts) = signed_in(&fs);
+
+        handle(conf, (false, false), &client, &fs).await.unwrap();
+
+        assert_eq!(*logouts.lock().unwrap(), 1);
+        let conf = Conf::load(&fs, None).unwrap();
+        assert!(!conf.is_logged_in());
+        let url = Url::parse(DSM_URL).unwrap();
+        assert_eq!(conf.get_device_id(&url).unwrap().as_str(), "device-1");
+    }
+
+    #[tokio::test]
+    async fn logout_local_only_and_forget() {
+        let fs = FakeFs::new();
+        let (conf, client, logouts) = signed_in(&fs);
+
+        handle(conf, (true, true), &client, &fs).await.unwrap();
+
+        assert_eq!(*logouts.lock().unwrap(), 0);
+        let conf = Conf::load(&fs, None).unwrap();
+        assert!(!conf.is_logged_in());
+        assert!(conf.device_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn logout_removes_session_when_dsm_is_unreachable() {
+        let fs = FakeFs::new();
+        let (conf, _, _) = signed_in(&fs);
+        let client = FakeHttpClient::new(|_| FakeResponse::error(119));
+
+        handle(conf, (false, false), &client, &fs).await.unwrap();
+
+        assert!(!Conf::load(&fs, None).unwrap().is_logged_in());
+    }
+}
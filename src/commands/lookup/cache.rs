@@ -0,0 +1,89 @@
+//! Album contents cache file ($HOME/.syno-photos-util-cache) support
+//!
+//! Listing items of every album is slow, so contents of albums are cached between runs of the
+//! `where` command. An album is listed again when its version or item count changes. People have
+//! no version, and their item count does not change when photos are re-assigned to another person,
+//! so people are listed on every run and not cached.
+
+use crate::fs::Fs;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AlbumCache {
+    entries: Vec<CacheEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CacheEntry {
+    url: String,
+    albums: Vec<CachedAlbum>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedAlbum {
+    /// "owned", "shared" or "person"
+    pub kind: String,
+    /// "personal" or "shared"
+    pub space: String,
+    pub id: u32,
+    pub name: String,
+    /// Album version and item count, used to detect changes; `None` for people, which are not
+    /// cached
+    pub stamp: Option<(u32, u32)>,
+    /// Item ids mapped to file names
+    pub items: BTreeMap<u32, String>,
+}
+
+impl CachedAlbum {
+    pub fn is_same(&self, other: &CachedAlbum) -> bool {
+        self.kind == other.kind && self.space == other.space && self.id == other.id
+    }
+}
+
+impl AlbumCache {
+    const CACHE_FILE: &'static str = ".syno-photos-util-cache";
+
+    /// Albums cached for DSM at `url`
+    pub fn albums(&self, url: &str) -> &[CachedAlbum] {
+        self.entries
+            .iter()
+            .find(|e| e.url == url)
+            .map_or(&[], |e| e.albums.as_slice())
+    }
+
+    /// Replace albums cached for DSM at `url`
+    pub fn set_albums(&mut self, url: &str, albums: Vec<CachedAlbum>) {
+        match self.entries.iter_mut().find(|e| e.url == url) {
+            Some(entry) => entry.albums = albums,
+            None => self.entries.push(CacheEntry {
+                url: url.to_string(),
+                albums,
+            }),
+        }
+    }
+
+    pub fn try_save<F: Fs>(&self, fs: &F) -> Result<()> {
+        let data = serde_json::to_string(self)?;
+        fs.write(Self::cache_path(fs)?, data.as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns empty cache when the file does not exist yet or cannot be read
+    pub fn load<F: Fs>(fs: &F) -> Self {
+        Self::cache_path(fs)
+            .ok()
+            .filter(|path| fs.exists(path))
+            .and_then(|path| fs.read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(data.as_str()).ok())
+            .unwrap_or_default()
+    }
+
+    fn cache_path<F: Fs>(fs: &F) -> Result<PathBuf> {
+        fs.home_dir()
+            .map(|home| home.join(Self::CACHE_FILE))
+            .ok_or(anyhow!("unable to find home dir"))
+    }
+}
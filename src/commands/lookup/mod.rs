@@ -0,0 +1,350 @@
+//! Reverse lookup of albums containing a photo (`where` command)
+
+use crate::commands::Album;
+use crate::commands::album::resolve_paths;
+use crate::commands::api_client::{ApiClient, PAGE_SIZE, SessionClient, Space};
+use crate::conf::Conf;
+use crate::fs::Fs;
+use crate::http::HttpClient;
+use crate::io::Io;
+use crate::output::{OutputFormat, Record, write_records};
+use anyhow::{Result, bail};
+use cache::{AlbumCache, CachedAlbum};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use syno_api::foto::setting::team_space::dto::TeamSpaceSettings;
+use syno_api::foto::setting::user::dto::UserSettings;
+
+pub(super) mod cache;
+
+/// Album containing an item
+#[derive(Debug, Serialize)]
+struct WhereRecord {
+    item_id: u32,
+    filename: String,
+    album: String,
+    /// "owned", "shared" or "person"
+    kind: String,
+    /// Space of the person; "personal" for albums
+    space: String,
+}
+
+impl Record for WhereRecord {
+    fn csv_header() -> &'static [&'static str] {
+        &["item_id", "filename", "album", "kind", "space"]
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.item_id.to_string(),
+            self.filename.clone(),
+            self.album.clone(),
+            self.kind.clone(),
+            self.space.clone(),
+        ]
+    }
+}
+
+pub async fn handle<C: HttpClient, I: Io, F: Fs>(
+    (query, refresh): (&str, bool),
+    output: OutputFormat,
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+    fs: &F,
+) -> Result<()> {
+    if !conf.is_logged_in() {
+        bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
+    }
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
+    let user_settings = client.get_user_settings().await?;
+    let team_space_settings = client.get_team_space_settings().await?;
+
+    let albums = album_contents(
+        (refresh, true),
+        (&user_settings, &team_space_settings),
+        &client,
+        fs,
+    )
+    .await?;
+
+    /* Items matching the query, mapped to file names (when known) */
+    let items: BTreeMap<u32, Option<String>> = if let Ok(id) = query.parse::<u32>() {
+        BTreeMap::from([(id, None)])
+    } else if query.contains(['/', '\\']) {
+        let (ids, _) = resolve_paths(query, conf, &client, io).await?;
+        ids.into_iter().map(|id| (id, None)).collect()
+    } else {
+        albums
+            .iter()
+            .flat_map(|a| a.items.iter())
+            .filter(|(_, filename)| matches_pattern(filename, query))
+            .map(|(id, filename)| (*id, Some(filename.clone())))
+            .collect()
+    };
+    let records: Vec<WhereRecord> = items
+        .keys()
+        .flat_map(|id| {
+            albums
+                .iter()
+                .filter_map(move |a| a.items.get(id).map(|filename| (*id, filename, a)))
+        })
+        .map(|(item_id, filename, album)| WhereRecord {
+            item_id,
+            filename: filename.clone(),
+            album: album.name.clone(),
+            kind: album.kind.clone(),
+            space: album.space.clone(),
+        })
+        .collect();
+    if output != OutputFormat::Text {
+        return write_records(&records, output, io.stdout());
+    }
+    if items.is_empty() {
+        writeln!(io.stdout(), "No photos found matching '{query}'")?;
+    }
+    for (id, filename) in items {
+        let item_records: Vec<&WhereRecord> = records.iter().filter(|r| r.item_id == id).collect();
+        let Some(first) = item_records.first() else {
+            match filename {
+                Some(filename) => writeln!(io.stdout(), "{filename} ({id}): not in any album")?,
+                None => writeln!(io.stdout(), "Item {id}: not in any album")?,
+            }
+            continue;
+        };
+        writeln!(io.stdout(), "{} ({id}):", first.filename)?;
+        for record in item_records {
+            let kind = match (record.kind.as_str(), record.space.as_str()) {
+                ("person", "shared") => "person in Shared Space",
+                ("person", _) => "person",
+                ("shared", _) => "shared with me",
+                _ => "owned",
+            };
+            writeln!(io.stdout(), "- \"{}\" ({kind})", record.album)?;
+        }
+    }
+    Ok(())
+}
+
+/// Contents of albums, and of people when `people` is set, taken from the cache when possible
+/// (unless `refresh` is set). The cache is updated with the listed albums.
+pub(super) async fn album_contents<C: ApiClient, F: Fs>(
+    (refresh, people): (bool, bool),
+    settings: (&UserSettings, &TeamSpaceSettings),
+    client: &SessionClient<'_, C>,
+    fs: &F,
+) -> Result<Vec<CachedAlbum>> {
+    let mut cache = if refresh {
+        AlbumCache::default()
+    } else {
+        AlbumCache::load(fs)
+    };
+    let url = client.dsm_url.as_str();
+    let albums = list_album_contents(cache.albums(url), people, settings, client).await?;
+    let cached_albums = albums
+        .iter()
+        .filter(|a| a.stamp.is_some())
+        .cloned()
+        .collect();
+    cache.set_albums(url, cached_albums);
+    if let Err(error) = cache.try_save(fs) {
+        log::warn!("saving album cache failed: {error}");
+    }
+    Ok(albums)
+}
+
+/// Contents of owned and shared-with-me albums, and of people in "People" auto-album when
+/// `people` is set (in the same spaces as [find_album](super::find_album) searches). Albums
+/// unchanged since they were cached are not listed again, people always are.
+async fn list_album_contents<C: ApiClient>(
+    cached_albums: &[CachedAlbum],
+    people: bool,
+    (user_settings, team_space_settings): (&UserSettings, &TeamSpaceSettings),
+    client: &SessionClient<'_, C>,
+) -> Result<Vec<CachedAlbum>> {
+    let mut albums: Vec<(CachedAlbum, Album)> = vec![];
+    let owned_albums = client
+        .all_pages(PAGE_SIZE, |c, page| c.list_owned_albums(page))
+        .await?;
+    let shared_albums = client
+        .all_pages(PAGE_SIZE, |c, page| c.list_shared_with_me_albums(page))
+        .await?;
+    for (kind, album) in owned_albums
+        .into_iter()
+        .map(|a| ("owned", a))
+        .chain(shared_albums.into_iter().map(|a| ("shared", a)))
+    {
+        let entry = CachedAlbum {
+            kind: kind.to_string(),
+            space: "personal".to_string(),
+            id: album.id,
+            name: album.name.clone(),
+            stamp: Some((album.version, album.item_count)),
+            items: BTreeMap::new(),
+        };
+        albums.push((entry, Album::Normal(album)));
+    }
+    let mut spaces = vec![];
+    if people && user_settings.enable_person {
+        spaces.push((Space::Personal, "personal"));
+    }
+    if people && team_space_settings.enable_person == Some(true) {
+        spaces.push((Space::Shared, "shared"));
+    }
+    for (space, space_name) in spaces {
+        let people = client
+            .all_pages(PAGE_SIZE, |c, page| c.list_people(space, page))
+            .await?;
+        for person in people {
+            let entry = CachedAlbum {
+                kind: "person".to_string(),
+                space: space_name.to_string(),
+                id: person.id,
+                name: person.name.clone(),
+                stamp: None,
+                items: BTreeMap::new(),
+            };
+            albums.push((entry, Album::Person(Box::new(person), space)));
+        }
+    }
+
+    let list_contents = |(mut entry, album): (CachedAlbum, Album)| async move {
+        let cached = cached_albums
+            .iter()
+            .find(|c| entry.stamp.is_some() && c.is_same(&entry) && c.stamp == entry.stamp);
+        entry.items = match cached {
+            Some(cached) => cached.items.clone(),
+            None => client
+                .list_all_items(&album)
+                .await?
+                .into_iter()
+                .map(|i| (i.id, i.filename))
+                .collect(),
+        };
+        Ok::<_, anyhow::Error>(entry)
+    };
+    const CONCURRENT_REQUESTS: usize = 4;
+    stream::iter(albums)
+        .map(list_contents)
+        .buffered(CONCURRENT_REQUESTS)
+        .try_collect()
+        .await
+}
+
+/// Case-insensitive match of `filename` against `pattern` with `*` (any characters) and `?` (any
+/// single character) wildcards
+pub(crate) fn matches_pattern(filename: &str, pattern: &str) -> bool {
+    let filename: Vec<char> = filename.to_lowercase().chars().collect();
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let (mut f, mut p) = (0, 0);
+    /* Position of the last '*' in pattern, and of filename when it was reached */
+    let mut backtrack = None;
+    while f < filename.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, f));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == filename[f] => {
+                p += 1;
+                f += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_f)) => {
+                    backtrack = Some((star_p, star_f + 1));
+                    p = star_p + 1;
+                    f = star_f + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[test]
+fn matches_pattern_with_wildcards() {
+    assert!(matches_pattern("IMG_1234.JPG", "img_1234.jpg"));
+    assert!(matches_pattern("IMG_1234.JPG", "IMG_*.jpg"));
+    assert!(matches_pattern("IMG_1234.JPG", "*12?4*"));
+    assert!(matches_pattern("a.b.jpg", "*.jpg"));
+    assert!(!matches_pattern("IMG_1234.JPG", "IMG_*.png"));
+    assert!(!matches_pattern("IMG_1234.JPG", "IMG"));
+}
+
+#[tokio::test]
+async fn album_contents_are_listed_again_when_changed() {
+    use crate::test::{FakeFs, FakeHttpClient, FakeResponse, logged_in_conf};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use syno_api::foto::browse::album::dto::Album as AlbumDto;
+    use syno_api::foto::browse::item::dto::Item;
+    use syno_api::foto::browse::person::dto::Person;
+
+    let album_version = Arc::new(AtomicU32::new(1));
+    let listed = Arc::new(Mutex::new(vec![]));
+    let (version, listed_contents) = (album_version.clone(), listed.clone());
+    let client = FakeHttpClient::new(move |request| {
+        let first_page = request.param("offset").as_deref() == Some("0");
+        let method = request.param("method").unwrap();
+        let list = match (request.param("api").unwrap().as_str(), method.as_str()) {
+            ("SYNO.Foto.Browse.Album", "list") if first_page => json!([AlbumDto {
+                id: 1,
+                name: "Trip".to_string(),
+                item_count: 1,
+                version: version.load(Ordering::SeqCst),
+                ..AlbumDto::default()
+            }]),
+            ("SYNO.Foto.Browse.Person", "list") if first_page => json!([Person {
+                id: 2,
+                name: "Alice".to_string(),
+                item_count: 1,
+                ..Person::default()
+            }]),
+            ("SYNO.Foto.Browse.Item", "list") if first_page => {
+                let id = request
+                    .param("album_id")
+                    .or_else(|| request.param("person_id"))
+                    .unwrap();
+                listed_contents.lock().unwrap().push(id);
+                json!([Item {
+                    id: 10,
+                    filename: "a.jpg".to_string(),
+                    ..Item::default()
+                }])
+            }
+            _ => json!([]),
+        };
+        FakeResponse::data(json!({ "list": list }))
+    });
+    let conf = logged_in_conf();
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), &client);
+    let user_settings = UserSettings {
+        enable_person: true,
+        ..UserSettings::default()
+    };
+    let settings = (&user_settings, &TeamSpaceSettings::default());
+    let fs = FakeFs::new();
+
+    let albums = album_contents((false, true), settings, &client, &fs)
+        .await
+        .unwrap();
+    assert_eq!(albums.len(), 2);
+    assert_eq!(*listed.lock().unwrap(), ["1", "2"]);
+
+    /* Unchanged album is taken from the cache, people are always listed */
+    album_contents((false, true), settings, &client, &fs)
+        .await
+        .unwrap();
+    assert_eq!(*listed.lock().unwrap(), ["1", "2", "2"]);
+
+    album_version.store(2, Ordering::SeqCst);
+    let albums = album_contents((false, true), settings, &client, &fs)
+        .await
+        .unwrap();
+    assert_eq!(*listed.lock().unwrap(), ["1", "2", "2", "1", "2"]);
+    assert!(albums.iter().all(|a| a.items.len() == 1));
+}
@@ -1,43 +1,53 @@
-use crate::commands::api_client::{ApiClient, SessionClient, Space};
+pub use crate::commands::api_client::Space;
+use crate::commands::api_client::{ApiClient, PAGE_SIZE, SessionClient};
+use crate::commands::dto::Place as PlaceDto;
 use crate::io::Io;
-use anyhow::Result;
+use anyhow::{Result, bail};
+use futures::{Stream, TryStreamExt};
 use std::io::Write;
+use std::pin::pin;
 pub use syno_api::error::Error as DsmError;
 use syno_api::foto::browse::album::dto::Album as AlbumDto;
+use syno_api::foto::browse::item::dto::Item;
 use syno_api::foto::browse::person::dto::Person as PersonDto;
+use syno_api::foto::error::PhotoError;
 use syno_api::foto::search::dto::Search;
 use syno_api::foto::setting::team_space::dto::TeamSpaceSettings;
 use syno_api::foto::setting::user::dto::UserSettings;
 
+pub mod album;
 mod api_client;
 pub mod check_update;
+pub mod download;
+mod dto;
+pub mod duplicates;
 mod error;
 pub mod export;
 pub mod list;
 pub mod list_albums;
+pub mod list_items;
 pub mod login;
 pub mod logout;
+pub mod lookup;
+pub mod orphans;
+pub mod profiles;
+pub mod run_jobs;
 pub mod status;
+pub mod tasks;
 
-// TODO add support for places album
 #[derive(Debug)]
 pub enum Album {
     Normal(AlbumDto),
-    Person(PersonDto, Space),
+    Person(Box<PersonDto>, Space),
+    Place(PlaceDto, Space),
 }
 
 impl Album {
-    pub fn item_count(&self) -> u32 {
-        match self {
-            Album::Normal(a) => a.item_count,
-            Album::Person(p, _) => p.item_count,
-        }
-    }
-
     pub fn name(&self) -> &str {
         match self {
             Album::Normal(a) => a.name.as_str(),
             Album::Person(p, _) => p.name.as_str(),
+            Album::Place(p, _) => p.name.as_str(),
         }
     }
 
@@ -51,85 +61,129 @@ impl Album {
                 }
             }
             Album::Person(p, _) => ("person_id", p.id.to_string()),
+            Album::Place(p, _) => ("geocoding_id", p.id.to_string()),
         }
     }
 }
 
-/// Search the API for album or person named `album_name` (case-insensitive)
+/// Search the API for album, person or place named `album_name` (case-insensitive)
 async fn find_album<C: ApiClient>(
     album_name: &str,
     user_settings: &UserSettings,
     team_space_settings: &TeamSpaceSettings,
     client: &SessionClient<'_, C>,
 ) -> Result<Option<Album>> {
-    /// Search among shared albums. There is no known API method to detect the number of
-    /// shared albums, we need to query the list in chunks until we find it or there are no
-    /// more albums returned.
-    async fn find_shared_album<C: ApiClient>(
-        album_name: &str,
-        client: &SessionClient<'_, C>,
-    ) -> Result<Option<AlbumDto>> {
-        let mut offset = 0;
-        const LIMIT: u32 = 50;
-        loop {
-            let shared_album_list = client
-                .list_shared_with_me_albums(offset, offset + LIMIT)
-                .await?;
-            if shared_album_list.is_empty() {
-                return Ok(None);
-            }
-            let shared_album = shared_album_list
-                .into_iter()
-                .find(|a| a.name.eq_ignore_ascii_case(album_name));
-            match shared_album {
-                Some(a) => return Ok(Some(a)),
-                None => offset += LIMIT,
-            }
-        }
-    }
-
-    async fn find_person_album<C: ApiClient>(
-        album_name: &str,
-        space: Space,
-        client: &SessionClient<'_, C>,
-    ) -> Result<Option<PersonDto>> {
-        let limit = client.count_people(space).await?;
-        let people = client.list_people(space, limit).await?;
-        let person = people
-            .into_iter()
-            .find(|p| p.name.eq_ignore_ascii_case(album_name));
-        Ok(person)
-    }
-
-    let owned_albums_count = client.count_owned_albums().await?;
-    let owned_album = if owned_albums_count > 0 {
-        let owned_album_list = client.list_owned_albums(owned_albums_count).await?;
-        owned_album_list
-            .into_iter()
-            .find(|a| a.name.eq_ignore_ascii_case(album_name))
-    } else {
-        None
-    };
+    let owned_album = find_in_pages(
+        client.pages(PAGE_SIZE, |c, page| c.list_owned_albums(page)),
+        |a| a.name.eq_ignore_ascii_case(album_name),
+    )
+    .await?;
     if let Some(album) = owned_album {
         return Ok(Some(Album::Normal(album)));
     }
-    let shared_album = find_shared_album(album_name, client).await?;
+    let shared_album = find_in_pages(
+        client.pages(PAGE_SIZE, |c, page| c.list_shared_with_me_albums(page)),
+        |a| a.name.eq_ignore_ascii_case(album_name),
+    )
+    .await?;
     if let Some(album) = shared_album {
         return Ok(Some(Album::Normal(album)));
     }
     if user_settings.enable_person {
-        let private_space_person_album = find_person_album(album_name, Space::Personal, client)
-            .await?
-            .map(|p| Album::Person(p, Space::Personal));
+        let private_space_person_album = find_in_pages(
+            client.pages(PAGE_SIZE, |c, page| c.list_people(Space::Personal, page)),
+            |p| p.name.eq_ignore_ascii_case(album_name),
+        )
+        .await?
+        .map(|p| Album::Person(Box::new(p), Space::Personal));
         if let Some(person_album) = private_space_person_album {
             return Ok(Some(person_album));
         }
     }
     if let Some(true) = team_space_settings.enable_person {
-        let shared_space_person_album = find_person_album(album_name, Space::Shared, client)
-            .await?
-            .map(|p| Album::Person(p, Space::Shared));
-        return Ok(shared_space_person_album);
+        let shared_space_person_album = find_in_pages(
+            client.pages(PAGE_SIZE, |c, page| c.list_people(Space::Shared, page)),
+            |p| p.name.eq_ignore_ascii_case(album_name),
+        )
+        .await?
+        .map(|p| Album::Person(Box::new(p), Space::Shared));
+        if let Some(person_album) = shared_space_person_album {
+            return Ok(Some(person_album));
+        }
+    }
+    if user_settings.enable_home_service {
+        let private_space_place_album = find_in_pages(
+            client.pages(PAGE_SIZE, |c, page| c.list_places(Space::Personal, page)),
+            |p| p.name.eq_ignore_ascii_case(album_name),
+        )
+        .await?
+        .map(|p| Album::Place(p, Space::Personal));
+        if let Some(place_album) = private_space_place_album {
+            return Ok(Some(place_album));
+        }
+    }
+    if team_space_settings.enabled && user_settings.team_space_permission != "none" {
+        let shared_space_place_album = find_in_pages(
+            client.pages(PAGE_SIZE, |c, page| c.list_places(Space::Shared, page)),
+            |p| p.name.eq_ignore_ascii_case(album_name),
+        )
+        .await?
+        .map(|p| Album::Place(p, Space::Shared));
+        return Ok(shared_space_place_album);
+    }
+    Ok(None)
+}
+
+/// Items in the folder, and in its subfolders when `recursive` is set
+async fn list_folder_tree<C: ApiClient>(
+    (folder_path, space): (&str, Space),
+    recursive: bool,
+    client: &SessionClient<'_, C>,
+) -> Result<Vec<Item>> {
+    let folder_path = format!("/{}", folder_path.trim().trim_matches('/'));
+    let folder = match client
+        .get_folder_by_name((folder_path.as_str(), space))
+        .await
+    {
+        Ok(folder) => folder,
+        Err(error) => match error.downcast::<DsmError>()? {
+            DsmError::Photo(PhotoError::NoAccessOrNotFound) => {
+                bail!("folder '{folder_path}' does not exist in {space}")
+            }
+            other => bail!(other),
+        },
+    };
+    let mut items = vec![];
+    let mut folder_ids = vec![folder.id];
+    while let Some(folder_id) = folder_ids.pop() {
+        let folder_items = client
+            .all_pages(PAGE_SIZE, |c, page| {
+                c.list_folder_items((folder_id, space), page)
+            })
+            .await?;
+        items.extend(folder_items);
+        if recursive {
+            let subfolders = client
+                .all_pages(PAGE_SIZE, |c, page| {
+                    c.list_subfolders((folder_id, space), page)
+                })
+                .await?;
+            folder_ids.extend(subfolders.into_iter().map(|f| f.id));
+        }
+    }
+    Ok(items)
+}
+
+/// Fetch `pages` until an entry matching `predicate` is found
+async fn find_in_pages<T, P: Fn(&T) -> bool>(
+    pages: impl Stream<Item = Result<Vec<T>>>,
+    predicate: P,
+) -> Result<Option<T>> {
+    let mut pages = pin!(pages);
+    while let Some(page) = pages.try_next().await? {
+        if let Some(entry) = page.into_iter().find(&predicate) {
+            return Ok(Some(entry));
+        }
     }
     Ok(None)
 }
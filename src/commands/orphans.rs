@@ -0,0 +1,212 @@
+use crate::commands::api_client::{PAGE_SIZE, SessionClient, Space};
+use crate::commands::list::path_mapping::PathMapper;
+use crate::commands::list::print_locations;
+use crate::commands::list_folder_tree;
+use crate::commands::lookup::album_contents;
+use crate::conf::Conf;
+use crate::fs::Fs;
+use crate::http::HttpClient;
+use crate::io::Io;
+use crate::output::OutputFormat;
+use anyhow::{Result, bail};
+use std::collections::HashSet;
+
+/// `(folder_path, shared, recursive)` - folder to look in instead of the whole timeline, whether
+/// it is in Shared Space, and whether to include subfolders
+pub type FolderArgs = (Option<String>, bool, bool);
+
+/// Print locations of photos which are not in any album
+///
+/// * `exclude_people` - do not report photos in "People" auto-album
+/// * `refresh` - ignore cached album contents
+pub async fn handle<C: HttpClient, I: Io, F: Fs>(
+    (folder_path, shared, recursive): FolderArgs,
+    (exclude_people, refresh): (bool, bool),
+    output: OutputFormat,
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+    fs: &F,
+) -> Result<()> {
+    if !conf.is_logged_in() {
+        bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
+    }
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
+    let user_settings = client.get_user_settings().await?;
+    let team_space_settings = client.get_team_space_settings().await?;
+
+    let photos = match folder_path {
+        Some(folder_path) => {
+            let space = if shared {
+                Space::Shared
+            } else {
+                Space::Personal
+            };
+            list_folder_tree((folder_path.as_str(), space), recursive, &client).await?
+        }
+        None => {
+            let mut photos = vec![];
+            if user_settings.enable_home_service {
+                photos.extend(
+                    client
+                        .all_pages(PAGE_SIZE, |c, page| {
+                            c.list_space_items(Space::Personal, page)
+                        })
+                        .await?,
+                );
+            }
+            if team_space_settings.enabled && user_settings.team_space_permission != "none" {
+                photos.extend(
+                    client
+                        .all_pages(PAGE_SIZE, |c, page| c.list_space_items(Space::Shared, page))
+                        .await?,
+                );
+            }
+            photos
+        }
+    };
+    let albums = album_contents(
+        (refresh, exclude_people),
+        (&user_settings, &team_space_settings),
+        &client,
+        fs,
+    )
+    .await?;
+    let album_items: HashSet<u32> = albums
+        .iter()
+        .flat_map(|a| a.items.keys().copied())
+        .collect();
+    let orphans = photos
+        .into_iter()
+        .filter(|p| !album_items.contains(&p.id))
+        .collect();
+    let path_mapper = PathMapper::new(
+        conf.path_mappings.clone(),
+        (conf.volume_paths, conf.homes_path.clone()),
+    );
+    print_locations(orphans, (&user_settings, &path_mapper), output, &client, io).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{FakeFs, FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use serde_json::json;
+    use syno_api::foto;
+    use syno_api::foto::browse::album::dto::Album as AlbumDto;
+    use syno_api::foto::browse::folder::dto::Folder;
+    use syno_api::foto::browse::item::dto::Item;
+    use syno_api::foto::browse::person::dto::Person;
+    use syno_api::foto::setting::team_space::dto::TeamSpaceSettings;
+    use syno_api::foto::setting::user::dto::UserSettings;
+    use syno_api::foto::user_info::dto::UserInfo;
+
+    /// Timeline of "a.jpg" in an album, "b.jpg" of a person and "c.jpg" in neither
+    fn client() -> FakeHttpClient {
+        FakeHttpClient::new(|request| {
+            let first_page = request.param("offset").as_deref() == Some("0");
+            if request.is(foto::setting::user::API, "get") {
+                return FakeResponse::data(UserSettings {
+                    enable_home_service: true,
+                    enable_person: true,
+                    team_space_permission: "none".to_string(),
+                });
+            }
+            if request.is(foto::setting::team_space::API, "get") {
+                return FakeResponse::data(TeamSpaceSettings::default());
+            }
+            if request.is(foto::browse::folder::API, "get") {
+                return FakeResponse::data(json!({"folder": Folder {
+                    id: 1,
+                    name: "/trips".to_string(),
+                    ..Folder::default()
+                }}));
+            }
+            if request.is(foto::user_info::API, "get") {
+                return FakeResponse::data(json!({"list": [UserInfo {
+                    id: 1,
+                    name: "alice".to_string(),
+                    ..UserInfo::default()
+                }]}));
+            }
+            let item = |id, filename: &str| Item {
+                id,
+                filename: filename.to_string(),
+                folder_id: 1,
+                owner_user_id: 1,
+                ..Item::default()
+            };
+            let list = match first_page {
+                true if request.is(foto::browse::album::API, "list") => json!([AlbumDto {
+                    id: 5,
+                    name: "Trips".to_string(),
+                    ..AlbumDto::default()
+                }]),
+                true if request.is(foto::browse::person::API, "list") => json!([Person {
+                    id: 7,
+                    name: "Bob".to_string(),
+                    ..Person::default()
+                }]),
+                true if request.is(foto::browse::item::API, "list") => {
+                    match (request.param("album_id"), request.param("person_id")) {
+                        (Some(_), _) => json!([item(10, "a.jpg")]),
+                        (_, Some(_)) => json!([item(11, "b.jpg")]),
+                        _ => json!([item(10, "a.jpg"), item(11, "b.jpg"), item(12, "c.jpg")]),
+                    }
+                }
+                _ => json!([]),
+            };
+            FakeResponse::data(json!({ "list": list }))
+        })
+    }
+
+    #[tokio::test]
+    async fn orphans_include_photos_of_people() {
+        let mut io = FakeIo::new("");
+
+        handle(
+            (None, false, false),
+            (false, true),
+            OutputFormat::Text,
+            &logged_in_conf(),
+            &client(),
+            &mut io,
+            &FakeFs::new(),
+        )
+        .await
+        .unwrap();
+
+        /* Locations are printed in no particular order */
+        let mut paths: Vec<_> = io.stdout_str().lines().map(str::to_string).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "/var/services/homes/alice/Photos/trips/b.jpg",
+                "/var/services/homes/alice/Photos/trips/c.jpg"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn orphans_exclude_people() {
+        let mut io = FakeIo::new("");
+
+        handle(
+            (None, false, false),
+            (true, true),
+            OutputFormat::Text,
+            &logged_in_conf(),
+            &client(),
+            &mut io,
+            &FakeFs::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            io.stdout_str(),
+            "/var/services/homes/alice/Photos/trips/c.jpg\n"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use crate::cli::ProfilesCommand;
+use crate::conf::{Conf, ConfFile};
+use crate::fs::Fs;
+use crate::io::Io;
+use anyhow::{Result, anyhow, bail};
+use std::io::Write;
+
+pub fn handle<F: Fs, I: Io>(command: Option<ProfilesCommand>, fs: &F, io: &mut I) -> Result<()> {
+    let mut conf_file = ConfFile::try_load(fs)?;
+    match command.unwrap_or(ProfilesCommand::List) {
+        ProfilesCommand::List => {
+            if !conf_file.profiles.contains_key(&conf_file.active_profile) {
+                /* Active profile has not been saved yet */
+                let active_profile = conf_file.active_profile.clone();
+                conf_file
+                    .profiles
+                    .insert(active_profile.clone(), Conf::new(active_profile));
+            }
+            for (name, conf) in &conf_file.profiles {
+                let marker = if *name == conf_file.active_profile {
+                    '*'
+                } else {
+                    ' '
+                };
+                match &conf.session {
+                    Some(session) => writeln!(io.stdout(), "{marker} {name} ({})", session.url)?,
+                    None => writeln!(io.stdout(), "{marker} {name} (signed out)")?,
+                }
+            }
+            Ok(())
+        }
+        ProfilesCommand::Switch { name } => {
+            if name.trim().is_empty() {
+                bail!("profile name should not be empty")
+            }
+            if !conf_file.profiles.contains_key(&name) {
+                writeln!(io.stdout(), "created profile '{name}'")?;
+                conf_file
+                    .profiles
+                    .insert(name.clone(), Conf::new(name.clone()));
+            }
+            conf_file.active_profile = name;
+            conf_file.try_save(fs)
+        }
+        ProfilesCommand::Remove { name } => {
+            let Some(conf) = conf_file.profiles.remove(&name) else {
+                bail!("profile '{name}' does not exist")
+            };
+            let conf = Conf {
+                profile: name.clone(),
+                ..conf
+            };
+            conf.delete_secrets(fs)
+                .map_err(|e| anyhow!("deleting secrets from {} failed: {e}", conf.secret_store))?;
+            if conf_file.active_profile == name {
+                conf_file.active_profile = Conf::DEFAULT_PROFILE.to_string();
+            }
+            conf_file.try_save(fs)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{DSM_URL, FakeFs, FakeIo, logged_in_conf};
+
+    fn list(fs: &FakeFs) -> String {
+        let mut io = FakeIo::new("");
+        handle(Some(ProfilesCommand::List), fs, &mut io).unwrap();
+        io.stdout_str()
+    }
+
+    #[test]
+    fn conf_of_previous_versions_becomes_default_profile() {
+        let fs = FakeFs::new();
+        let legacy_conf = serde_json::to_string(&logged_in_conf()).unwrap();
+        fs.write("/home/.syno-photos-util", legacy_conf).unwrap();
+
+        assert_eq!(list(&fs), format!("* default ({DSM_URL})\n"));
+        assert!(Conf::load(&fs, None).unwrap().is_logged_in());
+    }
+
+    #[test]
+    fn switch_creates_and_activates_profile() {
+        let fs = FakeFs::new();
+        logged_in_conf().try_save(&fs).unwrap();
+        let mut io = FakeIo::new("");
+
+        let name = "work".to_string();
+        handle(Some(ProfilesCommand::Switch { name }), &fs, &mut io).unwrap();
+
+        assert_eq!(io.stdout_str(), "created profile 'work'\n");
+        assert_eq!(
+            list(&fs),
+            format!("  default ({DSM_URL})\n* work (signed out)\n")
+        );
+        let conf = Conf::load(&fs, None).unwrap();
+        assert_eq!(conf.profile, "work");
+        assert!(!conf.is_logged_in());
+        assert!(Conf::load(&fs, Some("default")).unwrap().is_logged_in());
+    }
+
+    #[test]
+    fn remove_active_profile_activates_default() {
+        let fs = FakeFs::new();
+        logged_in_conf().try_save(&fs).unwrap();
+        let mut io = FakeIo::new("");
+        let name = "work".to_string();
+        handle(Some(ProfilesCommand::Switch { name }), &fs, &mut io).unwrap();
+        Conf::load(&fs, None).unwrap().try_save(&fs).unwrap();
+
+        let name = "work".to_string();
+        handle(Some(ProfilesCommand::Remove { name }), &fs, &mut io).unwrap();
+
+        assert_eq!(list(&fs), format!("* default ({DSM_URL})\n"));
+        let name = "work".to_string();
+        let error = handle(Some(ProfilesCommand::Remove { name }), &fs, &mut io).unwrap_err();
+        assert_eq!(error.to_string(), "profile 'work' does not exist");
+    }
+
+    #[test]
+    fn remove_deletes_secrets_of_profile() {
+        use crate::secrets::SecretBackend;
+
+        let fs = FakeFs::new();
+        let conf = |profile: &str| Conf {
+            profile: profile.to_string(),
+            secret_store: SecretBackend::File,
+            ..logged_in_conf()
+        };
+        conf("default").try_save(&fs).unwrap();
+        conf("work").try_save(&fs).unwrap();
+        let mut io = FakeIo::new("");
+
+        let name = "work".to_string();
+        handle(Some(ProfilesCommand::Remove { name }), &fs, &mut io).unwrap();
+
+        let secrets = fs
+            .read_to_string("/home/.syno-photos-util-secrets")
+            .unwrap();
+        assert!(!secrets.contains("work/"));
+        assert!(secrets.contains("default/session"));
+    }
+}
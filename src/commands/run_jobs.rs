@@ -0,0 +1,298 @@
+//! Export jobs listed in a TOML file (or a JSON one, with `.json` extension), e.g. one kept in
+//! version control
+//!
+//! ```toml
+//! [[jobs]]
+//! name = "Holidays"
+//! album = "Holidays 2024"
+//! folder = "/Exports/Holidays 2024"
+//! space = "shared"
+//! on_conflict = "overwrite"
+//! create = true
+//! ```
+
+use crate::commands::Space;
+use crate::commands::export::{self, ExportOptions, ExportSummary, OnConflict};
+use crate::conf::Conf;
+use crate::fs::Fs;
+use crate::http::HttpClient;
+use crate::io::{BufferIo, Io};
+use anyhow::{Result, anyhow, bail};
+use futures::{StreamExt, stream};
+use serde::Deserialize;
+use std::io::Write;
+use std::path::Path;
+use std::pin::pin;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobsFile {
+    jobs: Vec<Job>,
+}
+
+/// Single export, like one run of the 'export' command
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Job {
+    /// Shown in the report instead of the album name
+    name: Option<String>,
+    /// Album name; can be a person name in "People" or a place name in "Places" auto-album
+    #[serde(alias = "person")]
+    album: String,
+    /// Target folder path
+    folder: String,
+    #[serde(default)]
+    space: Space,
+    #[serde(default)]
+    on_conflict: OnConflict,
+    /// Create target folder if it does not exist
+    #[serde(default)]
+    create: bool,
+}
+
+impl Job {
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(self.album.as_str())
+    }
+}
+
+/// Number of jobs run at once with `parallel`
+const CONCURRENT_JOBS: usize = 4;
+
+/// Run export jobs from `jobs_file` in order, or a few at once when `parallel` is set. Output of
+/// parallel jobs is printed when each job finishes. Ctrl-C interrupts all parallel jobs waiting
+/// for copy tasks, which are left running, and no more jobs are started, as jobs in order stop
+/// after one is interrupted. Fails when any of the jobs failed.
+pub async fn handle<C: HttpClient, I: Io, F: Fs>(
+    (jobs_file, parallel): (&Path, bool),
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+    fs: &F,
+) -> Result<()> {
+    if !conf.is_logged_in() {
+        bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
+    }
+    let data = fs
+        .read_to_string(jobs_file)
+        .map_err(|e| anyhow!("unable to read {}: {e}", jobs_file.display()))?;
+    let JobsFile { jobs } = parse_jobs_file(jobs_file, data.as_str())
+        .map_err(|e| anyhow!("invalid jobs file {}: {e}", jobs_file.display()))?;
+    if jobs.is_empty() {
+        bail!("no jobs in {}", jobs_file.display())
+    }
+
+    let mut results = Vec::with_capacity(jobs.len());
+    if parallel {
+        let interrupted = AtomicBool::new(false);
+        let interrupted = &interrupted;
+        let mut finished_jobs = pin!(
+            stream::iter(&jobs)
+                .map(|job| async move {
+                    if interrupted.load(Ordering::SeqCst) {
+                        return None;
+                    }
+                    let mut job_io = BufferIo::new();
+                    let result = run_job(job, conf, client, &mut job_io, fs).await;
+                    if matches!(&result, Ok(summary) if summary.interrupted) {
+                        interrupted.store(true, Ordering::SeqCst);
+                    }
+                    Some((job_io, result))
+                })
+                .buffered(CONCURRENT_JOBS)
+        );
+        /* Jobs start in order, so the ones after the first not started are not started either */
+        while let Some(Some((job_io, result))) = finished_jobs.next().await {
+            print_job_header(&jobs, results.len(), io)?;
+            job_io.flush_to(io)?;
+            print_job_error(&result, io)?;
+            results.push(result);
+        }
+    } else {
+        for job in &jobs {
+            print_job_header(&jobs, results.len(), io)?;
+            let result = run_job(job, conf, client, io, fs).await;
+            print_job_error(&result, io)?;
+            let interrupted = matches!(&result, Ok(summary) if summary.interrupted);
+            results.push(result);
+            if interrupted {
+                break;
+            }
+        }
+    }
+    print_report(&jobs, &results, io)
+}
+
+/// TOML, or JSON when the file name ends with `.json`
+fn parse_jobs_file(jobs_file: &Path, data: &str) -> Result<JobsFile> {
+    let is_json = jobs_file
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    if is_json {
+        Ok(serde_json::from_str(data)?)
+    } else {
+        Ok(toml::from_str(data)?)
+    }
+}
+
+async fn run_job<C: HttpClient, I: Io, F: Fs>(
+    job: &Job,
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+    fs: &F,
+) -> Result<ExportSummary> {
+    export::handle(
+        slice::from_ref(&job.album),
+        (job.folder.as_str(), job.space),
+        ExportOptions {
+            create_folder: job.create,
+            on_conflict: job.on_conflict,
+            require_album: true,
+            ..ExportOptions::default()
+        },
+        conf,
+        client,
+        io,
+        fs,
+    )
+    .await
+}
+
+fn print_job_header<I: Io>(jobs: &[Job], index: usize, io: &mut I) -> Result<()> {
+    let job = &jobs[index];
+    writeln!(
+        io.stdout(),
+        "Job {}/{}: '{}' to folder '{}' in {}",
+        index + 1,
+        jobs.len(),
+        job.name(),
+        job.folder,
+        job.space
+    )?;
+    Ok(())
+}
+
+fn print_job_error<I: Io>(result: &Result<ExportSummary>, io: &mut I) -> Result<()> {
+    if let Err(error) = result {
+        writeln!(io.stdout(), "Error: {error}")?;
+    }
+    Ok(())
+}
+
+/// A job failed unless all its items were either copied or skipped. Jobs not run (after an
+/// interrupted one) are reported as failed too.
+fn print_report<I: Io>(jobs: &[Job], results: &[Result<ExportSummary>], io: &mut I) -> Result<()> {
+    writeln!(io.stdout(), "Report:")?;
+    let mut failed = 0;
+    for (index, job) in jobs.iter().enumerate() {
+        let name = job.name();
+        match results.get(index) {
+            Some(Ok(summary)) if summary.is_complete() => writeln!(
+                io.stdout(),
+                "- '{name}': ok ({} copied, {} skipped, {} failed)",
+                summary.copied,
+                summary.skipped,
+                summary.failed
+            )?,
+            Some(Ok(summary)) => {
+                writeln!(io.stdout(), "- '{name}': failed ({summary})")?;
+                failed += 1;
+            }
+            Some(Err(error)) => {
+                writeln!(io.stdout(), "- '{name}': failed ({error})")?;
+                failed += 1;
+            }
+            None => {
+                writeln!(io.stdout(), "- '{name}': not run")?;
+                failed += 1;
+            }
+        }
+    }
+    if failed != 0 {
+        bail!("{failed} of {} job(s) failed", jobs.len())
+    }
+    Ok(())
+}
+
+#[test]
+fn parse_jobs_file_with_defaults() {
+    let JobsFile { jobs } = parse_jobs_file(
+        Path::new("jobs.toml"),
+        r#"
+            [[jobs]]
+            album = "Holidays"
+            folder = "/Exports"
+            space = "shared"
+            on_conflict = "rename"
+
+            [[jobs]]
+            name = "Alice"
+            person = "Alice Smith"
+            folder = "/People/Alice"
+            create = true
+        "#,
+    )
+    .unwrap();
+    assert_eq!(jobs[0].name(), "Holidays");
+    assert_eq!(jobs[0].space, Space::Shared);
+    assert_eq!(jobs[0].on_conflict, OnConflict::Rename);
+    assert!(!jobs[0].create);
+    assert_eq!(jobs[1].name(), "Alice");
+    assert_eq!(jobs[1].album, "Alice Smith");
+    assert_eq!(jobs[1].space, Space::Personal);
+    assert_eq!(jobs[1].on_conflict, OnConflict::Skip);
+    assert!(jobs[1].create);
+
+    let JobsFile { jobs } = parse_jobs_file(
+        Path::new("jobs.JSON"),
+        r#"{"jobs": [{"album": "Holidays", "folder": "/Exports"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(jobs[0].name(), "Holidays");
+    assert!(parse_jobs_file(Path::new("jobs.toml"), "[[jobs]]\nalbum = 1").is_err());
+}
+
+#[test]
+fn report_fails_jobs_with_failed_or_canceled_items() {
+    let job = |name: &str| Job {
+        name: Some(name.to_string()),
+        album: name.to_string(),
+        folder: "/Exports".to_string(),
+        space: Space::Personal,
+        on_conflict: OnConflict::Skip,
+        create: false,
+    };
+    let jobs = [job("Trips"), job("Pets"), job("Work"), job("Rest")];
+    let results = [
+        Ok(ExportSummary {
+            copied: 3,
+            skipped: 1,
+            ..ExportSummary::default()
+        }),
+        Ok(ExportSummary {
+            copied: 2,
+            failed: 1,
+            aborted: 2,
+            interrupted: true,
+            ..ExportSummary::default()
+        }),
+        Err(anyhow!("album 'Work' not found")),
+    ];
+    let mut io = crate::test::FakeIo::new("");
+
+    let result = print_report(&jobs, &results, &mut io);
+
+    assert_eq!(result.unwrap_err().to_string(), "3 of 4 job(s) failed");
+    assert_eq!(
+        io.stdout_str(),
+        "Report:\n\
+         - 'Trips': ok (3 copied, 1 skipped, 0 failed)\n\
+         - 'Pets': failed (2 copied (0 conflicting, overwritten or renamed), 0 skipped, 1 failed, \
+         2 canceled)\n\
+         - 'Work': failed (album 'Work' not found)\n\
+         - 'Rest': not run\n"
+    );
+}
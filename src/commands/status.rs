@@ -1,23 +1,212 @@
+use crate::commands::api_client::SessionClient;
+use crate::commands::error::is_session_error;
 use crate::conf::Conf;
+use crate::http::HttpClient;
 use crate::io::Io;
+use crate::output::{OutputFormat, Record, write_records};
 use anyhow::Result;
+use serde::Serialize;
 use std::io::Write;
 
-pub fn handle<I: Io>(conf: &Conf, io: &mut I) -> Result<()> {
-    match conf.is_logged_in() {
-        true => {
-            writeln!(
-                io.stdout(),
-                "signed in to {}",
-                conf.session.as_ref().unwrap().url
-            )?;
+#[derive(Debug, Default, Serialize)]
+struct StatusRecord {
+    signed_in: bool,
+    url: Option<String>,
+    /// "valid", "expired" or "unverified" (e.g. when DSM is unreachable)
+    session: Option<&'static str>,
+    user: Option<String>,
+    personal_space: Option<bool>,
+    shared_space: Option<bool>,
+    device_id_remembered: Option<bool>,
+}
+
+impl Record for StatusRecord {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "signed_in",
+            "url",
+            "session",
+            "user",
+            "personal_space",
+            "shared_space",
+            "device_id_remembered",
+        ]
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(T::to_string).unwrap_or_default()
         }
-        false => {
-            writeln!(
-                io.stdout(),
-                "signed out, use the 'login' command to sign-in to DSM"
-            )?;
+
+        vec![
+            self.signed_in.to_string(),
+            opt(&self.url),
+            opt(&self.session),
+            opt(&self.user),
+            opt(&self.personal_space),
+            opt(&self.shared_space),
+            opt(&self.device_id_remembered),
+        ]
+    }
+}
+
+pub async fn handle<C: HttpClient, I: Io>(
+    conf: &Conf,
+    output: OutputFormat,
+    client: &C,
+    io: &mut I,
+) -> Result<()> {
+    let record = match &conf.session {
+        None => StatusRecord::default(),
+        Some(session) => {
+            let mut record = StatusRecord {
+                signed_in: true,
+                url: Some(session.url.to_string()),
+                device_id_remembered: Some(conf.get_device_id(&session.url).is_some()),
+                ..StatusRecord::default()
+            };
+            /* Getting user settings is cheap and requires a valid session */
+            let client = SessionClient::new(session, client);
+            match client.get_user_settings().await {
+                Ok(user_settings) => {
+                    record.session = Some("valid");
+                    record.personal_space = Some(user_settings.enable_home_service);
+                    record.shared_space = Some(user_settings.team_space_permission != "none");
+                    record.user = client
+                        .get_user_name()
+                        .await
+                        .inspect_err(|e| log::warn!("getting user name failed: {e}"))
+                        .ok();
+                }
+                Err(error) if is_session_error(&error) => record.session = Some("expired"),
+                Err(error) => {
+                    log::warn!("unable to verify session: {error}");
+                    record.session = Some("unverified");
+                }
+            }
+            record
         }
+    };
+    if output != OutputFormat::Text {
+        return write_records(&[record], output, io.stdout());
+    }
+    print_status(record, io)
+}
+
+fn print_status<I: Io>(record: StatusRecord, io: &mut I) -> Result<()> {
+    fn availability(available: bool) -> &'static str {
+        if available {
+            "available"
+        } else {
+            "not available"
+        }
+    }
+
+    let (Some(url), Some(session)) = (&record.url, record.session) else {
+        writeln!(
+            io.stdout(),
+            "signed out, use the 'login' command to sign-in to DSM"
+        )?;
+        return Ok(());
+    };
+    match (session, &record.user) {
+        ("expired", _) => writeln!(
+            io.stdout(),
+            "session expired on {url}, use the 'login' command to sign-in again"
+        )?,
+        (_, Some(user)) => writeln!(io.stdout(), "signed in to {url} as {user}")?,
+        (_, None) => writeln!(io.stdout(), "signed in to {url}")?,
+    }
+    writeln!(io.stdout(), "session: {session}")?;
+    if let (Some(personal_space), Some(shared_space)) = (record.personal_space, record.shared_space)
+    {
+        writeln!(
+            io.stdout(),
+            "Personal Space: {}",
+            availability(personal_space)
+        )?;
+        writeln!(io.stdout(), "Shared Space: {}", availability(shared_space))?;
+    }
+    if let Some(remembered) = record.device_id_remembered {
+        let remembered = if remembered { "yes" } else { "no" };
+        writeln!(io.stdout(), "remembered device: {remembered}")?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::login::creds::DeviceId;
+    use crate::test::{DSM_URL, FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use serde_json::json;
+    use syno_api::foto;
+    use syno_api::foto::setting::user::dto::UserSettings;
+
+    #[tokio::test]
+    async fn status_of_valid_session() {
+        let client = FakeHttpClient::new(|request| {
+            if request.is(foto::user_info::API, "me") {
+                return FakeResponse::data(json!({"name": "alice"}));
+            }
+            assert!(request.is(foto::setting::user::API, "get"));
+            FakeResponse::data(UserSettings {
+                enable_home_service: true,
+                team_space_permission: "none".to_string(),
+                ..UserSettings::default()
+            })
+        });
+        let mut conf = logged_in_conf();
+        conf.set_device_id(DeviceId::new("device-1".to_string()).unwrap());
+        let mut io = FakeIo::new("");
+
+        handle(&conf, OutputFormat::Text, &client, &mut io)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            io.stdout_str(),
+            format!(
+                "signed in to {DSM_URL} as alice\n\
+                 session: valid\n\
+                 Personal Space: available\n\
+                 Shared Space: not available\n\
+                 remembered device: yes\n"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn status_of_expired_session_as_csv() {
+        let client = FakeHttpClient::new(|_| FakeResponse::error(106));
+        let mut io = FakeIo::new("");
+
+        handle(&logged_in_conf(), OutputFormat::Csv, &client, &mut io)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            io.stdout_str(),
+            format!(
+                "signed_in,url,session,user,personal_space,shared_space,device_id_remembered\n\
+                 true,{DSM_URL},expired,,,,false\n"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn status_when_signed_out() {
+        let client = FakeHttpClient::new(|_| panic!("DSM should not be called"));
+        let conf = Conf::new(Conf::DEFAULT_PROFILE.to_string());
+        let mut io = FakeIo::new("");
+
+        handle(&conf, OutputFormat::Text, &client, &mut io)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            io.stdout_str(),
+            "signed out, use the 'login' command to sign-in to DSM\n"
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! Extra methods for SessionClient used by tasks and export commands
+
+use crate::commands::api_client::{ApiClient, ApiParams, SessionClient};
+use anyhow::Result;
+use serde::de::IgnoredAny;
+use syno_api::dto::List;
+use syno_api::foto;
+use syno_api::foto::background_task::file::dto::TaskInfo;
+
+impl<C: ApiClient> SessionClient<'_, C> {
+    pub async fn get_task_status(&self, task_ids: &[u32]) -> Result<Vec<TaskInfo>> {
+        let ids = task_ids
+            .iter()
+            .map(u32::to_string)
+            .reduce(|acc, id| format!("{acc},{id}"))
+            .expect("task_ids should not be empty");
+        let task_infos: List<TaskInfo> = self
+            .client
+            .get(
+                self.dsm_url.clone(),
+                ApiParams::new(foto::background_task::info::API, "get_status", 1),
+                &[("id", format!("[{ids}]").as_str())],
+            )
+            .await?;
+        Ok(task_infos.list)
+    }
+
+    /// Request the tasks to stop; their status is "aborting" until they do
+    pub async fn abort_tasks(&self, task_ids: &[u32]) -> Result<()> {
+        let ids = task_ids
+            .iter()
+            .map(u32::to_string)
+            .reduce(|acc, id| format!("{acc},{id}"))
+            .expect("task_ids should not be empty");
+        let _: IgnoredAny = self
+            .client
+            .post(
+                self.dsm_url.clone(),
+                ApiParams::new(foto::background_task::info::API, "abort", 1),
+                &[("id", format!("[{ids}]").as_str())],
+            )
+            .await?;
+        Ok(())
+    }
+}
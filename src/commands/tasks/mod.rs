@@ -0,0 +1,251 @@
+use crate::cli::TasksCommand;
+use crate::commands::api_client::{ApiClient, SessionClient};
+use crate::conf::Conf;
+use crate::http::HttpClient;
+use crate::io::Io;
+use crate::output::{OutputFormat, Record, write_records};
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::io::Write;
+use std::time::Duration;
+use syno_api::foto::background_task::file::dto::TaskInfo;
+#[cfg(test)]
+use test::fake_sleep as sleep;
+#[cfg(not(test))]
+use tokio::time::sleep;
+
+mod api_client;
+
+/// Status of a background (copy) task
+#[derive(Debug, Serialize)]
+struct TaskRecord {
+    task_id: u32,
+    /// "waiting", "processing", "aborting", or a final status, e.g. "done"
+    status: String,
+    total: u32,
+    /// Number of processed items, including skipped and failed ones
+    completion: u32,
+    skipped: u32,
+    /// Existing files which were overwritten or renamed
+    conflicts: u32,
+    failed: u32,
+}
+
+impl Record for TaskRecord {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "task_id",
+            "status",
+            "total",
+            "completion",
+            "skipped",
+            "conflicts",
+            "failed",
+        ]
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.task_id.to_string(),
+            self.status.clone(),
+            self.total.to_string(),
+            self.completion.to_string(),
+            self.skipped.to_string(),
+            self.conflicts.to_string(),
+            self.failed.to_string(),
+        ]
+    }
+}
+
+impl From<&TaskInfo> for TaskRecord {
+    fn from(task: &TaskInfo) -> Self {
+        TaskRecord {
+            task_id: task.id,
+            status: task.status.clone(),
+            total: task.total,
+            completion: task.completion,
+            skipped: task.skip,
+            conflicts: task.overwrite,
+            failed: task.error,
+        }
+    }
+}
+
+pub async fn handle<C: HttpClient, I: Io>(
+    command: TasksCommand,
+    output: OutputFormat,
+    conf: &Conf,
+    client: &C,
+    io: &mut I,
+) -> Result<()> {
+    if !conf.is_logged_in() {
+        bail!("you are not signed in to DSM, use the 'login' command (see '--help' for details)");
+    }
+    let client = SessionClient::new(conf.session.as_ref().unwrap(), client);
+    match command {
+        TasksCommand::Status { task_ids } => {
+            let tasks = client.get_task_status(&task_ids).await?;
+            print_tasks(&tasks, output, io)
+        }
+        TasksCommand::Wait { task_ids } => {
+            let tasks = wait_for_tasks(task_ids, &client, io).await?;
+            print_tasks(&tasks, output, io)
+        }
+        TasksCommand::Cancel { task_ids } => {
+            client.abort_tasks(&task_ids).await?;
+            for id in task_ids {
+                writeln!(io.stdout(), "Canceling task {id}")?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn print_tasks<I: Io>(tasks: &[TaskInfo], output: OutputFormat, io: &mut I) -> Result<()> {
+    let records: Vec<TaskRecord> = tasks.iter().map(TaskRecord::from).collect();
+    if output != OutputFormat::Text {
+        return write_records(&records, output, io.stdout());
+    }
+    for record in records {
+        writeln!(
+            io.stdout(),
+            "Task {}: {}, {} of {} items processed ({} skipped, {} overwritten or renamed, {} failed)",
+            record.task_id,
+            record.status,
+            record.completion,
+            record.total,
+            record.skipped,
+            record.conflicts,
+            record.failed
+        )?;
+    }
+    Ok(())
+}
+
+/// Poll the API until the tasks finish, printing progress dots to stderr, so they don't mix with
+/// the status records. Returns final task infos.
+pub(crate) async fn wait_for_tasks<C: ApiClient, I: Io>(
+    mut task_ids: Vec<u32>,
+    client: &SessionClient<'_, C>,
+    io: &mut I,
+) -> Result<Vec<TaskInfo>> {
+    let mut finished = vec![];
+    let mut dot_print_counter = 0;
+    loop {
+        if task_ids.is_empty() {
+            writeln!(io.stderr())?;
+            break;
+        }
+        sleep(Duration::from_secs(1)).await;
+
+        if dot_print_counter == 3 {
+            write!(io.stderr(), "\r   \r")?;
+            dot_print_counter = 0;
+        } else {
+            write!(io.stderr(), ".")?;
+            dot_print_counter += 1;
+        }
+        io.stderr().flush()?;
+
+        let updated_task_infos = client.get_task_status(&task_ids).await?;
+        let (processing, done): (Vec<_>, Vec<_>) =
+            updated_task_infos.into_iter().partition(is_running);
+        task_ids = processing.into_iter().map(|t| t.id).collect();
+        finished.extend(done);
+    }
+    Ok(finished)
+}
+
+pub(crate) fn is_running(task: &TaskInfo) -> bool {
+    task.status == "waiting" || task.status == "processing" || task.status == "aborting"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{FakeHttpClient, FakeIo, FakeResponse, logged_in_conf};
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use syno_api::foto;
+
+    pub(super) async fn fake_sleep(_: Duration) {}
+
+    fn task(id: u32, status: &str) -> TaskInfo {
+        TaskInfo {
+            id,
+            status: status.to_string(),
+            total: 10,
+            completion: 6,
+            skip: 2,
+            overwrite: 1,
+            error: 1,
+            ..TaskInfo::default()
+        }
+    }
+
+    #[test]
+    fn task_record_maps_task_counts() {
+        let record = TaskRecord::from(&task(7, "done"));
+
+        assert_eq!(record.csv_values(), ["7", "done", "10", "6", "2", "1", "1"]);
+        assert_eq!(record.csv_values().len(), TaskRecord::csv_header().len());
+    }
+
+    #[test]
+    fn print_tasks_in_text_and_csv() {
+        let tasks = [task(7, "done"), task(8, "processing")];
+
+        let mut io = FakeIo::new("");
+        print_tasks(&tasks, OutputFormat::Text, &mut io).unwrap();
+        assert_eq!(
+            io.stdout_str(),
+            "Task 7: done, 6 of 10 items processed (2 skipped, 1 overwritten or renamed, 1 failed)\n\
+             Task 8: processing, 6 of 10 items processed \
+             (2 skipped, 1 overwritten or renamed, 1 failed)\n"
+        );
+
+        let mut io = FakeIo::new("");
+        print_tasks(&tasks, OutputFormat::Csv, &mut io).unwrap();
+        assert_eq!(
+            io.stdout_str(),
+            "task_id,status,total,completion,skipped,conflicts,failed\n\
+             7,done,10,6,2,1,1\n\
+             8,processing,10,6,2,1,1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_tasks_keeps_progress_off_stdout() {
+        let polls = Arc::new(AtomicU32::new(0));
+        let client = {
+            let polls = polls.clone();
+            FakeHttpClient::new(move |request| {
+                assert!(request.is(foto::background_task::info::API, "get_status"));
+                let status = match polls.fetch_add(1, Ordering::SeqCst) {
+                    0 => "processing",
+                    _ => "done",
+                };
+                FakeResponse::data(json!({"list": [task(7, status)]}))
+            })
+        };
+        let conf = logged_in_conf();
+        let mut io = FakeIo::new("");
+
+        handle(
+            TasksCommand::Wait { task_ids: vec![7] },
+            OutputFormat::Json,
+            &conf,
+            &client,
+            &mut io,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(polls.load(Ordering::SeqCst), 2);
+        let records: serde_json::Value = serde_json::from_str(&io.stdout_str()).unwrap();
+        assert_eq!(records[0]["task_id"], 7);
+        assert_eq!(records[0]["status"], "done");
+        assert_eq!(io.stderr_str(), "..\n");
+    }
+}
@@ -1,37 +1,91 @@
 //! Session file ($HOME/.syno-photos-util) support
+//!
+//! The file holds a number of named profiles (e.g. for multiple NAS boxes), each with its own
+//! session, device ids and settings.
 
+use crate::commands::list::path_mapping::PathMapping;
 use crate::commands::login::creds::DeviceId;
 use crate::fs::Fs;
 use crate::http::Url;
-use anyhow::{Result, anyhow};
+use crate::secrets::{SecretBackend, secret_store};
+use anyhow::{Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::io;
 use std::path::PathBuf;
 
 #[cfg(unix)]
 use std::{fs::Permissions, os::unix::fs::PermissionsExt};
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Settings of a single profile
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Conf {
+    /// Name of the profile, stored as key in [ConfFile]
+    #[serde(skip)]
+    pub profile: String,
     pub session: Option<Session>,
     pub device_ids: HashMap<String, DeviceId>,
+    /// Rules rewriting paths printed by list command
+    #[serde(default)]
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub path_mappings: Vec<PathMapping>,
+    /// Print real `/volumeN` paths in list command
+    #[serde(default)]
+    pub volume_paths: bool,
+    /// Location of the homes shared folder in `/volumeN` paths, `/volume1/homes` when not set
+    #[serde(default)]
+    pub homes_path: Option<String>,
+    /// Where session cookie, device ids and credentials are stored
+    #[serde(default)]
+    pub secret_store: SecretBackend,
+    /// Only stored when using a secret store
+    #[serde(skip)]
+    pub credentials: Option<Credentials>,
+    /// Secret store which could not be read when loading the profile. Saving to it would replace
+    /// the secrets with the missing ones.
+    #[serde(skip)]
+    pub(crate) unreadable_secret_store: Option<SecretBackend>,
+}
+
+/// DSM user account credentials
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
 }
 
 #[serde_as]
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Session {
     #[serde_as(as = "DisplayFromStr")]
     pub url: Url,
     pub cookie: String,
 }
 
+/// Contents of the conf file
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfFile {
+    pub active_profile: String,
+    pub profiles: BTreeMap<String, Conf>,
+}
+
 impl Conf {
-    pub fn new() -> Self {
+    pub const DEFAULT_PROFILE: &'static str = "default";
+
+    pub fn new(profile: String) -> Self {
         Conf {
+            profile,
             session: None,
             device_ids: HashMap::new(),
+            path_mappings: vec![],
+            volume_paths: false,
+            homes_path: None,
+            secret_store: SecretBackend::Conf,
+            credentials: None,
+            unreadable_secret_store: None,
         }
     }
 
@@ -61,6 +115,115 @@ impl Conf {
         }
     }
 
+    /// Load `profile`, or the active one when `None`. Returns new (empty) profile when it does not
+    /// exist yet. Fails when conf file cannot be read, so that it is not saved over.
+    pub fn load<F: Fs>(fs: &F, profile: Option<&str>) -> Result<Self> {
+        let mut conf_file = ConfFile::try_load(fs)?;
+        let profile = profile
+            .map(str::to_string)
+            .unwrap_or(conf_file.active_profile);
+        match conf_file.profiles.remove(&profile) {
+            Some(conf) => {
+                let mut conf = Conf { profile, ..conf };
+                if let Err(error) = conf.load_secrets(fs) {
+                    log::warn!(
+                        "reading secrets from {} failed: {error}; continuing signed out",
+                        conf.secret_store
+                    );
+                    conf.session = None;
+                    conf.unreadable_secret_store = Some(conf.secret_store);
+                }
+                Ok(conf)
+            }
+            None => Ok(Conf::new(profile)),
+        }
+    }
+
+    /// Store the profile in conf file, preserving other profiles. When a secret store is used,
+    /// session cookie, device ids and credentials are written there instead. Fails when the
+    /// secret store could not be read by [Conf::load].
+    pub fn try_save<F: Fs>(&self, fs: &F) -> Result<()> {
+        if self.unreadable_secret_store == Some(self.secret_store) {
+            bail!(
+                "secrets of profile '{}' could not be read from {}, not saving over them; \
+                 fix access to it, or use 'login --secret-store' to switch to another one",
+                self.profile,
+                self.secret_store
+            )
+        }
+        let mut conf = self.clone();
+        if let Some(store) = secret_store(self.secret_store, fs) {
+            match &self.session {
+                Some(session) => store.set(&self.secret_key("session"), &session.cookie)?,
+                None => store.delete(&self.secret_key("session"))?,
+            }
+            store.set(
+                &self.secret_key("device_ids"),
+                &serde_json::to_string(&self.device_ids)?,
+            )?;
+            match &self.credentials {
+                Some(credentials) => store.set(
+                    &self.secret_key("credentials"),
+                    &serde_json::to_string(credentials)?,
+                )?,
+                None => store.delete(&self.secret_key("credentials"))?,
+            }
+            if let Some(session) = &mut conf.session {
+                session.cookie.clear();
+            }
+            conf.device_ids.clear();
+        }
+        let mut conf_file = ConfFile::try_load(fs)?;
+        conf_file.profiles.insert(self.profile.clone(), conf);
+        conf_file.try_save(fs)
+    }
+
+    fn load_secrets<F: Fs>(&mut self, fs: &F) -> Result<()> {
+        let Some(store) = secret_store(self.secret_store, fs) else {
+            return Ok(());
+        };
+        if self.session.is_some() {
+            match store.get(&self.secret_key("session"))? {
+                Some(cookie) => self.session.as_mut().unwrap().cookie = cookie,
+                /* E.g. kernel keyring is cleared on reboot */
+                None => self.session = None,
+            }
+        }
+        if let Some(device_ids) = store.get(&self.secret_key("device_ids"))? {
+            self.device_ids = serde_json::from_str(device_ids.as_str())?;
+        }
+        if let Some(credentials) = store.get(&self.secret_key("credentials"))? {
+            self.credentials = Some(serde_json::from_str(credentials.as_str())?);
+        }
+        Ok(())
+    }
+
+    /// Remove session cookie, device ids and credentials of the profile from its secret store
+    pub fn delete_secrets<F: Fs>(&self, fs: &F) -> Result<()> {
+        let Some(store) = secret_store(self.secret_store, fs) else {
+            return Ok(());
+        };
+        for name in ["session", "device_ids", "credentials"] {
+            store.delete(&self.secret_key(name))?;
+        }
+        Ok(())
+    }
+
+    fn secret_key(&self, name: &str) -> String {
+        format!("{}/{name}", self.profile)
+    }
+}
+
+impl Default for ConfFile {
+    fn default() -> Self {
+        ConfFile {
+            active_profile: Conf::DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl ConfFile {
     const CONF_FILE: &'static str = ".syno-photos-util";
     #[cfg(unix)]
     const OWNER_RW: u32 = 0o600;
@@ -77,27 +240,45 @@ impl Conf {
         Ok(())
     }
 
-    pub fn try_load<F: Fs>(fs: &F) -> Option<Self> {
-        Self::conf_path(fs)
-            .map_or(None, |conf_path| {
-                #[cfg(unix)]
-                {
-                    let _ = fs.metadata(&conf_path).map(|m| {
-                        /* & 0o777 removes file type part from the mode. Otherwise, the mode is
-                         * 6 octal digits instead of 3 */
-                        let mode = m.permissions().mode() & 0o777;
-                        if mode != Self::OWNER_RW {
-                            log::warn!(
-                                "{} mode is {mode:o}, should be {:o}",
-                                conf_path.display(),
-                                Self::OWNER_RW
-                            );
-                        }
-                    });
+    /// Profiles in conf file, none when it does not exist. Fails when it cannot be read or parsed.
+    pub fn try_load<F: Fs>(fs: &F) -> Result<Self> {
+        let conf_path = Self::conf_path(fs)?;
+        let data = match fs.read_to_string(&conf_path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(ConfFile::default()),
+            Err(error) => bail!("unable to read {}: {error}", conf_path.display()),
+        };
+        #[cfg(unix)]
+        {
+            let _ = fs.metadata(&conf_path).map(|m| {
+                /* & 0o777 removes file type part from the mode. Otherwise, the mode is
+                 * 6 octal digits instead of 3 */
+                let mode = m.permissions().mode() & 0o777;
+                if mode != Self::OWNER_RW {
+                    log::warn!(
+                        "{} mode is {mode:o}, should be {:o}",
+                        conf_path.display(),
+                        Self::OWNER_RW
+                    );
                 }
-                fs.read_to_string(conf_path).ok()
+            });
+        }
+        serde_json::from_str(data.as_str())
+            .or_else(|error| {
+                /* Files written by previous versions contain a single profile */
+                serde_json::from_str::<Conf>(data.as_str())
+                    .map(|conf| ConfFile {
+                        profiles: BTreeMap::from([(Conf::DEFAULT_PROFILE.to_string(), conf)]),
+                        ..ConfFile::default()
+                    })
+                    .map_err(|_| error)
+            })
+            .map_err(|e| {
+                anyhow!(
+                    "invalid conf file {}: {e}; fix or remove it",
+                    conf_path.display()
+                )
             })
-            .and_then(|data| serde_json::from_str(data.as_str()).ok())
     }
 
     fn conf_path<F: Fs>(fs: &F) -> Result<PathBuf> {
@@ -111,3 +292,101 @@ impl Conf {
         env::temp_dir().join(Self::CONF_FILE)
     }
 }
+
+#[test]
+fn try_save_moves_secrets_to_secret_store() {
+    use crate::commands::login::creds::DeviceId;
+    use crate::test::{DSM_URL, FakeFs, logged_in_conf};
+
+    let fs = FakeFs::new();
+    let mut conf = Conf {
+        secret_store: SecretBackend::File,
+        credentials: Some(Credentials {
+            user: "alice".to_string(),
+            password: "secret password".to_string(),
+        }),
+        ..logged_in_conf()
+    };
+    conf.set_device_id(DeviceId::new("device-1".to_string()).unwrap());
+    conf.try_save(&fs).unwrap();
+
+    let conf_data = fs.read_to_string("/home/.syno-photos-util").unwrap();
+    assert!(!conf_data.contains("id=session"));
+    assert!(!conf_data.contains("device-1"));
+    assert!(!conf_data.contains("secret password"));
+    let loaded = Conf::load(&fs, None).unwrap();
+    let url = Url::parse(DSM_URL).unwrap();
+    assert_eq!(loaded.get_device_id(&url).unwrap().as_str(), "device-1");
+    assert_eq!(loaded.session.unwrap().cookie, "id=session");
+    assert_eq!(loaded.credentials.unwrap().password, "secret password");
+
+    /* Signing out removes the session and credentials from the store */
+    conf.session = None;
+    conf.credentials = None;
+    conf.try_save(&fs).unwrap();
+    let loaded = Conf::load(&fs, None).unwrap();
+    assert!(loaded.session.is_none());
+    assert!(loaded.credentials.is_none());
+    assert_eq!(loaded.device_ids.len(), 1);
+}
+
+#[test]
+fn load_secrets_drops_session_missing_in_secret_store() {
+    use crate::test::{FakeFs, logged_in_conf};
+
+    let fs = FakeFs::new();
+    let conf = Conf {
+        secret_store: SecretBackend::File,
+        ..logged_in_conf()
+    };
+    conf.try_save(&fs).unwrap();
+    assert!(Conf::load(&fs, None).unwrap().is_logged_in());
+
+    /* E.g. kernel keyring is cleared on reboot */
+    fs.remove_file("/home/.syno-photos-util-secrets").unwrap();
+    assert!(!Conf::load(&fs, None).unwrap().is_logged_in());
+}
+
+#[test]
+fn invalid_conf_file_is_not_saved_over() {
+    use crate::test::{FakeFs, logged_in_conf};
+
+    let fs = FakeFs::new();
+    fs.write("/home/.syno-photos-util", "{\"profiles\": {")
+        .unwrap();
+
+    assert!(Conf::load(&fs, None).is_err());
+    assert!(logged_in_conf().try_save(&fs).is_err());
+    assert_eq!(
+        fs.read_to_string("/home/.syno-photos-util").unwrap(),
+        "{\"profiles\": {"
+    );
+}
+
+#[test]
+fn unreadable_secret_store_is_not_saved_over() {
+    use crate::test::{FakeFs, logged_in_conf};
+
+    let fs = FakeFs::new();
+    let conf = Conf {
+        secret_store: SecretBackend::File,
+        ..logged_in_conf()
+    };
+    conf.try_save(&fs).unwrap();
+    /* E.g. a locked keyring */
+    fs.write("/home/.syno-photos-util-secrets", "locked")
+        .unwrap();
+
+    let mut loaded = Conf::load(&fs, None).unwrap();
+    assert!(!loaded.is_logged_in());
+    assert!(loaded.try_save(&fs).is_err());
+    assert_eq!(
+        fs.read_to_string("/home/.syno-photos-util-secrets")
+            .unwrap(),
+        "locked"
+    );
+
+    /* Switching to another store is allowed */
+    loaded.secret_store = SecretBackend::Conf;
+    loaded.try_save(&fs).unwrap();
+}
@@ -1,10 +1,17 @@
 //! Isolates file-system operations for testing
 
-use std::fs::{Metadata, Permissions};
+use std::fs::{File, Metadata, OpenOptions, Permissions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use std::{fs, io};
 
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
 pub trait Fs {
+    type File: Write;
+
     fn home_dir(&self) -> Option<PathBuf>;
     fn exists(&self, path: &Path) -> bool;
     fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String>;
@@ -13,11 +20,19 @@ pub trait Fs {
     fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()>;
     fn set_permissions<P: AsRef<Path>>(&self, path: P, perm: Permissions) -> io::Result<()>;
     fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata>;
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()>;
+    fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File>;
+    /// Like [Fs::create], but a new file is readable only by the owner (on Unix)
+    fn create_private<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File>;
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()>;
+    fn set_modified<P: AsRef<Path>>(&self, path: P, time: SystemTime) -> io::Result<()>;
 }
 
 pub struct FsImpl;
 
 impl Fs for FsImpl {
+    type File = File;
+
     fn home_dir(&self) -> Option<PathBuf> {
         dirs::home_dir()
     }
@@ -49,4 +64,31 @@ impl Fs for FsImpl {
     fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
         fs::metadata(path)
     }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File> {
+        File::create(path)
+    }
+
+    fn create_private<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        options.open(path)
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn set_modified<P: AsRef<Path>>(&self, path: P, time: SystemTime) -> io::Result<()> {
+        OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_modified(time)
+    }
 }
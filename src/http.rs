@@ -17,6 +17,12 @@ pub trait HttpClient {
         url: U,
         form: &F,
     ) -> impl Future<Output = Result<Self::Response>>;
+
+    /// Sign in to DSM again when the session has expired. Returns `true` when the failed request
+    /// can be retried.
+    fn relogin(&self) -> impl Future<Output = Result<bool>> {
+        async { Ok(false) }
+    }
 }
 
 /// Isolates [reqwest::Response] for testing
@@ -25,6 +31,8 @@ pub trait HttpResponse {
     fn status(&self) -> StatusCode;
     fn text(self) -> impl Future<Output = Result<String>>;
     fn json<T: DeserializeOwned + 'static>(self) -> impl Future<Output = Result<T>>;
+    /// Next chunk of response body, `None` when body has been read entirely
+    fn chunk(&mut self) -> impl Future<Output = Result<Option<Vec<u8>>>>;
 }
 
 impl HttpClient for ReqwestClient {
@@ -51,6 +59,10 @@ impl HttpResponse for ReqwestResponse {
     async fn json<T: DeserializeOwned>(self) -> Result<T> {
         Ok(ReqwestResponse::json(self).await?)
     }
+
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(ReqwestResponse::chunk(self).await?.map(|b| b.to_vec()))
+    }
 }
 
 pub struct CookieClient<C: HttpClient, S: CookieStore> {
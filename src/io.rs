@@ -1,11 +1,11 @@
 //! Isolates IO and PasswordReader for testing
 
 use anyhow::Result;
-use std::io::{BufRead, IsTerminal, Stderr, StdinLock, Stdout, Write};
+use std::io::{BufRead, Empty, Stderr, StdinLock, Stdout, Write};
 use yapp::{PasswordReader, Yapp};
 
 pub trait Io {
-    type StdIn: BufRead + IsTerminal;
+    type StdIn: BufRead;
     type StdOut: Write;
     type StdErr: Write;
     type PasswordReader: PasswordReader;
@@ -14,6 +14,11 @@ pub trait Io {
     fn stdout(&mut self) -> &mut Self::StdOut;
     fn stderr(&mut self) -> &mut Self::StdErr;
     fn password_reader(&mut self) -> &mut Self::PasswordReader;
+
+    /// Whether the user can answer prompts, i.e. reading input does not always get end of file
+    fn can_prompt(&self) -> bool {
+        true
+    }
 }
 
 pub(crate) fn read_input<I: Io>(prompt: &str, io: &mut I) -> Result<String> {
@@ -71,3 +76,57 @@ impl Default for IoImpl {
         Self::new()
     }
 }
+
+/// Collects output in memory, e.g. to print it at once when commands run concurrently. Reading
+/// input always gets end of file.
+pub(crate) struct BufferIo {
+    stdin: Empty,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    password_reader: Yapp,
+}
+
+impl Io for BufferIo {
+    type StdIn = Empty;
+    type StdOut = Vec<u8>;
+    type StdErr = Vec<u8>;
+    type PasswordReader = Yapp;
+
+    fn stdin(&mut self) -> &mut Self::StdIn {
+        &mut self.stdin
+    }
+
+    fn stdout(&mut self) -> &mut Self::StdOut {
+        &mut self.stdout
+    }
+
+    fn stderr(&mut self) -> &mut Self::StdErr {
+        &mut self.stderr
+    }
+
+    fn password_reader(&mut self) -> &mut Self::PasswordReader {
+        &mut self.password_reader
+    }
+
+    fn can_prompt(&self) -> bool {
+        false
+    }
+}
+
+impl BufferIo {
+    pub fn new() -> Self {
+        BufferIo {
+            stdin: std::io::empty(),
+            stdout: vec![],
+            stderr: vec![],
+            password_reader: Yapp::default(),
+        }
+    }
+
+    /// Write collected output to `io`
+    pub fn flush_to<I: Io>(self, io: &mut I) -> Result<()> {
+        io.stdout().write_all(&self.stdout)?;
+        io.stderr().write_all(&self.stderr)?;
+        Ok(())
+    }
+}
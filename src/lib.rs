@@ -1,14 +1,23 @@
+use crate::commands::export::ExportOptions;
 use crate::http::HeaderValue;
 pub use crate::{cli::Cli, fs::FsImpl, http::CookieClient, io::IoImpl};
 use crate::{
     cli::Command,
-    commands::{check_update, export, list, list_albums, login, logout, status},
+    commands::{
+        album, check_update, download, duplicates, export, list, list_albums, list_items,
+        login::{
+            self,
+            relogin::{ReloginClient, find_credentials},
+        },
+        logout, lookup, orphans, profiles, run_jobs, status, tasks,
+    },
     conf::Conf,
     fs::Fs,
     http::{CookieStore, HttpClient},
     io::Io,
+    output::OutputFormat,
 };
-use anyhow::Result;
+use anyhow::{Result, bail};
 
 mod cli;
 mod commands;
@@ -16,6 +25,8 @@ mod conf;
 mod fs;
 mod http;
 mod io;
+mod output;
+mod secrets;
 
 #[cfg(test)]
 mod test;
@@ -27,23 +38,39 @@ pub async fn run<I: Io, C: HttpClient, S: CookieStore, F: Fs>(
     fs: &F,
     installed_version: &str,
 ) -> Result<()> {
-    let mut conf = Conf::try_load(fs).unwrap_or_else(Conf::new);
+    if cli.output != OutputFormat::Text && !cli.command.supports_output_format() {
+        bail!("this command only supports '--output text'");
+    }
+    let mut conf = Conf::load(fs, cli.profile.as_deref())?;
     if let Some(session) = &conf.session {
         let cookie = HeaderValue::from_str(session.cookie.as_str())?;
         client
             .cookie_store
             .set_cookies(&mut [cookie].iter(), &session.url);
     }
+    let credentials = if cli.relogin {
+        let credentials = find_credentials(cli.credentials_file.as_deref(), &conf, fs)?;
+        if credentials.is_none() {
+            log::warn!("no credentials found, unable to sign in again when session expires");
+        }
+        credentials
+    } else {
+        None
+    };
+    let relogin_client = ReloginClient::new(&conf, credentials, client, fs);
     match cli.command {
         Command::Login {
             dsm_url,
             user,
             password,
             remember,
+            secret_store,
+            save_password,
         } => {
             login::handle(
                 dsm_url,
                 (user, password, remember),
+                (secret_store, save_password),
                 &mut conf,
                 client,
                 io,
@@ -52,28 +79,140 @@ pub async fn run<I: Io, C: HttpClient, S: CookieStore, F: Fs>(
             .await
         }
         Command::ListAlbums { owned, shared } => {
-            list_albums::handle((owned, shared), &conf, &client.client, io).await
+            list_albums::handle((owned, shared), cli.output, &conf, &relogin_client, io).await
+        }
+        Command::List {
+            album_name,
+            path_mappings,
+            volume_paths,
+            homes_path,
+            save,
+        } => {
+            list::handle(
+                album_name.as_str(),
+                (path_mappings, (volume_paths, homes_path), save),
+                cli.output,
+                &mut conf,
+                &relogin_client,
+                io,
+                fs,
+            )
+            .await
         }
-        Command::List { album_name } => {
-            list::handle(album_name.as_str(), &conf, &client.client, io).await
+        Command::ListItems { album_name } => {
+            list_items::handle(album_name.as_str(), cli.output, &conf, &relogin_client, io).await
         }
+        Command::Where { query, refresh } => {
+            lookup::handle(
+                (query.as_str(), refresh),
+                cli.output,
+                &conf,
+                &relogin_client,
+                io,
+                fs,
+            )
+            .await
+        }
+        Command::Orphans {
+            folder_path,
+            shared,
+            recursive,
+            exclude_people,
+            refresh,
+        } => {
+            orphans::handle(
+                (folder_path, shared, recursive),
+                (exclude_people, refresh),
+                cli.output,
+                &conf,
+                &relogin_client,
+                io,
+                fs,
+            )
+            .await
+        }
+        Command::Duplicates {
+            albums,
+            folders,
+            shared_folders,
+            recursive,
+            hash,
+        } => {
+            duplicates::handle(
+                (albums, folders, shared_folders, recursive),
+                hash,
+                cli.output,
+                &conf,
+                &relogin_client,
+                io,
+            )
+            .await
+        }
+        Command::Album { command } => album::handle(command, &conf, &relogin_client, io, fs).await,
         Command::Export {
-            album_name,
+            album_names,
             create,
             folder_path,
+            space,
+            on_conflict,
+            sync,
+            prune,
+            dry_run,
+            detach,
+        } => export::handle(
+            &album_names,
+            (folder_path.as_str(), space),
+            ExportOptions {
+                create_folder: create,
+                on_conflict,
+                sync,
+                prune,
+                dry_run,
+                detach,
+                require_album: false,
+            },
+            &conf,
+            &relogin_client,
+            io,
+            fs,
+        )
+        .await
+        .map(|_| ()),
+        Command::RunJobs {
+            jobs_file,
+            parallel,
         } => {
-            export::handle(
+            run_jobs::handle(
+                (jobs_file.as_path(), parallel),
+                &conf,
+                &relogin_client,
+                io,
+                fs,
+            )
+            .await
+        }
+        Command::Tasks { command } => {
+            tasks::handle(command, cli.output, &conf, &relogin_client, io).await
+        }
+        Command::Download {
+            album_name,
+            directory,
+        } => {
+            download::handle(
                 album_name.as_str(),
-                folder_path.as_str(),
-                create,
+                directory.as_path(),
                 &conf,
-                &client.client,
+                &relogin_client,
                 io,
+                fs,
             )
             .await
         }
-        Command::Logout { forget } => logout::handle(conf, forget, fs),
-        Command::Status => status::handle(&conf, io),
+        Command::Logout { forget, local_only } => {
+            logout::handle(conf, (forget, local_only), &client.client, fs).await
+        }
+        Command::Status => status::handle(&conf, cli.output, &relogin_client, io).await,
         Command::CheckUpdate => check_update::handle(installed_version, &client.client, io).await,
+        Command::Profiles { command } => profiles::handle(command, fs, io),
     }
 }
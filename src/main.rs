@@ -23,7 +23,8 @@ async fn main() -> Result<()> {
     let mut client = CookieClient {
         client: ClientBuilder::default()
             .cookie_provider(cookie_store.clone())
-            .timeout(cli.timeout_seconds)
+            .connect_timeout(cli.timeout_seconds)
+            .read_timeout(cli.timeout_seconds)
             .build()?,
         cookie_store,
     };
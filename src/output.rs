@@ -0,0 +1,75 @@
+//! Machine-readable output of command results
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON array of records
+    Json,
+    /// Newline-delimited JSON, one record per line
+    Ndjson,
+    /// Comma-separated values with a header line
+    Csv,
+}
+
+/// Command result record which can be written in any of the machine-readable formats
+pub trait Record: Serialize {
+    fn csv_header() -> &'static [&'static str];
+    /// Values in the same order as [Record::csv_header]
+    fn csv_values(&self) -> Vec<String>;
+}
+
+/// Write `records` in one of the machine-readable formats. Text output is specific to each
+/// command, so it is not handled here.
+pub fn write_records<W: Write, R: Record>(
+    records: &[R],
+    format: OutputFormat,
+    out: &mut W,
+) -> Result<()> {
+    match format {
+        OutputFormat::Text => unreachable!("text output should be written by the command"),
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, records)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "{}", R::csv_header().join(","))?;
+            for record in records {
+                let values: Vec<_> = record.csv_values().iter().map(|v| csv_escape(v)).collect();
+                writeln!(out, "{}", values.join(","))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Quote value if it contains a separator, quote or line break (RFC 4180)
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[test]
+fn csv_escape_quotes_special_characters() {
+    assert_eq!(csv_escape("plain.jpg"), "plain.jpg");
+    assert_eq!(csv_escape("a,b.jpg"), "\"a,b.jpg\"");
+    assert_eq!(
+        csv_escape("say \"cheese\".jpg"),
+        "\"say \"\"cheese\"\".jpg\""
+    );
+}
@@ -0,0 +1,285 @@
+//! Storage of session cookie, device ids and DSM credentials outside of the conf file
+//!
+//! Secret Service and kernel keyring are accessed with `secret-tool` (libsecret) and `keyctl`
+//! (keyutils) command line tools respectively, which need to be installed.
+
+use crate::fs::Fs;
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+#[cfg(unix)]
+use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+#[derive(
+    Debug, Default, Display, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretBackend {
+    /// Store secrets in the conf file, along with other settings
+    #[default]
+    #[display("conf file")]
+    Conf,
+    /// Secret Service, e.g. GNOME Keyring or KWallet (requires `secret-tool`)
+    #[display("Secret Service")]
+    SecretService,
+    /// Linux kernel keyring, kept until reboot (requires `keyctl`)
+    #[display("kernel keyring")]
+    Keyring,
+    /// Separate $HOME/.syno-photos-util-secrets file; a stand-in for systems without keyring
+    #[display("secrets file")]
+    File,
+}
+
+pub trait SecretStore {
+    fn get(&self, key: &str) -> Result<Option<String>>;
+    fn set(&self, key: &str, secret: &str) -> Result<()>;
+    fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// Returns `None` for [SecretBackend::Conf]
+pub fn secret_store<'a, F: Fs>(
+    backend: SecretBackend,
+    fs: &'a F,
+) -> Option<Box<dyn SecretStore + 'a>> {
+    match backend {
+        SecretBackend::Conf => None,
+        SecretBackend::SecretService => Some(Box::new(SecretServiceStore)),
+        SecretBackend::Keyring => Some(Box::new(KeyringStore)),
+        SecretBackend::File => Some(Box::new(FileSecretStore { fs })),
+    }
+}
+
+const SERVICE: &str = "syno-photos-util";
+
+fn run(command: &mut Command, stdin: Option<&str>) -> Result<Output> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("unable to run {program}: {e}"))?;
+    if let Some(input) = stdin {
+        child
+            .stdin
+            .take()
+            .expect("stdin should be piped")
+            .write_all(input.as_bytes())?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).trim().to_string()
+}
+
+struct SecretServiceStore;
+
+impl SecretStore for SecretServiceStore {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let output = run(
+            Command::new("secret-tool").args(["lookup", "service", SERVICE, "key", key]),
+            None,
+        )?;
+        /* secret-tool exits with 1 and no output when the secret does not exist */
+        if !output.status.success() {
+            let error = stderr(&output);
+            return if error.is_empty() {
+                Ok(None)
+            } else {
+                Err(anyhow!(error))
+            };
+        }
+        Ok(Some(String::from_utf8(output.stdout)?))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        let label = format!("--label={SERVICE} {key}");
+        let output = run(
+            Command::new("secret-tool").args([
+                "store",
+                label.as_str(),
+                "service",
+                SERVICE,
+                "key",
+                key,
+            ]),
+            Some(secret),
+        )?;
+        if !output.status.success() {
+            bail!(stderr(&output))
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let output = run(
+            Command::new("secret-tool").args(["clear", "service", SERVICE, "key", key]),
+            None,
+        )?;
+        if !output.status.success() {
+            bail!(stderr(&output))
+        }
+        Ok(())
+    }
+}
+
+/// Stores secrets as "user" keys in the user keyring (@u)
+struct KeyringStore;
+
+impl KeyringStore {
+    fn description(key: &str) -> String {
+        format!("{SERVICE}:{key}")
+    }
+
+    fn search(&self, key: &str) -> Result<Option<String>> {
+        let output = run(
+            Command::new("keyctl").args(["search", "@u", "user", &Self::description(key)]),
+            None,
+        )?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8(output.stdout)?.trim().to_string()))
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let Some(id) = self.search(key)? else {
+            return Ok(None);
+        };
+        let output = run(Command::new("keyctl").args(["pipe", id.as_str()]), None)?;
+        if !output.status.success() {
+            bail!(stderr(&output))
+        }
+        Ok(Some(String::from_utf8(output.stdout)?))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        let output = run(
+            Command::new("keyctl").args(["padd", "user", &Self::description(key), "@u"]),
+            Some(secret),
+        )?;
+        if !output.status.success() {
+            bail!(stderr(&output))
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let Some(id) = self.search(key)? else {
+            return Ok(());
+        };
+        let output = run(
+            Command::new("keyctl").args(["unlink", id.as_str(), "@u"]),
+            None,
+        )?;
+        if !output.status.success() {
+            bail!(stderr(&output))
+        }
+        Ok(())
+    }
+}
+
+/// Stores secrets in a JSON file, readable only by the owner
+struct FileSecretStore<'a, F: Fs> {
+    fs: &'a F,
+}
+
+impl<F: Fs> FileSecretStore<'_, F> {
+    const SECRETS_FILE: &'static str = ".syno-photos-util-secrets";
+
+    fn path(&self) -> Result<PathBuf> {
+        self.fs
+            .home_dir()
+            .map(|home| home.join(Self::SECRETS_FILE))
+            .ok_or(anyhow!("unable to find home dir"))
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>> {
+        let path = self.path()?;
+        if !self.fs.exists(&path) {
+            return Ok(BTreeMap::new());
+        }
+        Ok(serde_json::from_str(
+            self.fs.read_to_string(path)?.as_str(),
+        )?)
+    }
+
+    /// The file is created readable only by the owner, so that secrets are never exposed
+    fn write(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let path = self.path()?;
+        let mut file = self.fs.create_private(&path)?;
+        /* A file which already exists keeps its mode; it is empty at this point */
+        #[cfg(unix)]
+        self.fs
+            .set_permissions(&path, Permissions::from_mode(0o600))?;
+        file.write_all(serde_json::to_string(secrets)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl<F: Fs> SecretStore for FileSecretStore<'_, F> {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.read()?.remove(key))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        let mut secrets = self.read()?;
+        secrets.insert(key.to_string(), secret.to_string());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let mut secrets = self.read()?;
+        if secrets.remove(key).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn file_secret_store_round_trip() {
+    let fs = crate::test::FakeFs::new();
+    let store = FileSecretStore { fs: &fs };
+    assert_eq!(store.get("default/session").unwrap(), None);
+    /* E.g. created by hand with the default mode */
+    fs.write("/home/.syno-photos-util-secrets", "{}").unwrap();
+    #[cfg(unix)]
+    fs.set_permissions(
+        "/home/.syno-photos-util-secrets",
+        Permissions::from_mode(0o644),
+    )
+    .unwrap();
+
+    store.set("default/session", "id=1").unwrap();
+    store.set("work/session", "id=2").unwrap();
+    store.set("default/session", "id=3").unwrap();
+    assert_eq!(
+        store.get("default/session").unwrap().as_deref(),
+        Some("id=3")
+    );
+    assert_eq!(store.get("work/session").unwrap().as_deref(), Some("id=2"));
+    #[cfg(unix)]
+    assert_eq!(
+        fs.metadata("/home/.syno-photos-util-secrets")
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o777,
+        0o600
+    );
+
+    store.delete("default/session").unwrap();
+    store.delete("missing").unwrap();
+    assert_eq!(store.get("default/session").unwrap(), None);
+    assert_eq!(store.get("work/session").unwrap().as_deref(), Some("id=2"));
+}
@@ -1,2 +1,284 @@
-#[test]
-fn todo() {}
+//! Fakes of the isolated IO, file-system and HTTP client used by command tests
+
+use crate::conf::{Conf, Session};
+use crate::fs::{Fs, FsImpl};
+use crate::http::{HttpClient, HttpResponse, Url};
+use crate::io::Io;
+use anyhow::Result;
+use reqwest::{IntoUrl, StatusCode};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::fs::{File, Metadata, Permissions};
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tempfile::TempDir;
+use yapp::Yapp;
+
+pub(crate) const DSM_URL: &str = "http://nas.test:5000/";
+
+/// Profile signed in to [DSM_URL]
+pub(crate) fn logged_in_conf() -> Conf {
+    Conf {
+        session: Some(Session {
+            url: Url::parse(DSM_URL).unwrap(),
+            cookie: "id=session".to_string(),
+        }),
+        ..Conf::new(Conf::DEFAULT_PROFILE.to_string())
+    }
+}
+
+/// Reads `input` as stdin and collects output in memory
+pub(crate) struct FakeIo {
+    stdin: Cursor<Vec<u8>>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    password_reader: Yapp,
+}
+
+impl FakeIo {
+    pub fn new(input: &str) -> Self {
+        FakeIo {
+            stdin: Cursor::new(input.as_bytes().to_vec()),
+            stdout: vec![],
+            stderr: vec![],
+            password_reader: Yapp::default(),
+        }
+    }
+
+    pub fn stdout_str(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    pub fn stderr_str(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).into_owned()
+    }
+}
+
+impl Io for FakeIo {
+    type StdIn = Cursor<Vec<u8>>;
+    type StdOut = Vec<u8>;
+    type StdErr = Vec<u8>;
+    type PasswordReader = Yapp;
+
+    fn stdin(&mut self) -> &mut Self::StdIn {
+        &mut self.stdin
+    }
+
+    fn stdout(&mut self) -> &mut Self::StdOut {
+        &mut self.stdout
+    }
+
+    fn stderr(&mut self) -> &mut Self::StdErr {
+        &mut self.stderr
+    }
+
+    fn password_reader(&mut self) -> &mut Self::PasswordReader {
+        &mut self.password_reader
+    }
+}
+
+/// Keeps files in a temporary directory, removed on drop. Absolute paths (including the home dir
+/// and the system temp dir) are mapped inside it, so tests don't touch real files.
+pub(crate) struct FakeFs {
+    root: TempDir,
+}
+
+impl FakeFs {
+    pub fn new() -> Self {
+        let fs = FakeFs {
+            root: TempDir::new().expect("temp dir should be created"),
+        };
+        FsImpl
+            .create_dir_all(fs.path("/home"))
+            .expect("home dir should be created");
+        fs
+    }
+
+    /// Real location of `path`
+    pub fn path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        self.root
+            .path()
+            .join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// Location of `path` with parent directories created
+    fn new_path<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let path = self.path(path);
+        if let Some(parent) = path.parent() {
+            FsImpl.create_dir_all(parent)?;
+        }
+        Ok(path)
+    }
+}
+
+impl Fs for FakeFs {
+    type File = File;
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        Some(PathBuf::from("/home"))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        FsImpl.exists(&self.path(path))
+    }
+
+    fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        FsImpl.read_to_string(self.path(path))
+    }
+
+    fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P, contents: C) -> io::Result<()> {
+        FsImpl.write(self.new_path(path)?, contents)
+    }
+
+    fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<u64> {
+        FsImpl.copy(self.path(from), self.new_path(to)?)
+    }
+
+    fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        FsImpl.remove_file(self.path(path))
+    }
+
+    fn set_permissions<P: AsRef<Path>>(&self, path: P, perm: Permissions) -> io::Result<()> {
+        FsImpl.set_permissions(self.path(path), perm)
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        FsImpl.metadata(self.path(path))
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        FsImpl.create_dir_all(self.path(path))
+    }
+
+    fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File> {
+        FsImpl.create(self.new_path(path)?)
+    }
+
+    fn create_private<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File> {
+        FsImpl.create_private(self.new_path(path)?)
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        FsImpl.rename(self.path(from), self.new_path(to)?)
+    }
+
+    fn set_modified<P: AsRef<Path>>(&self, path: P, time: SystemTime) -> io::Result<()> {
+        FsImpl.set_modified(self.path(path), time)
+    }
+}
+
+/// Request received by [FakeHttpClient]
+#[derive(Debug, Clone)]
+pub(crate) struct Request {
+    pub url: Url,
+    /// Form of a POST request
+    pub form: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Value of query or form parameter
+    pub fn param(&self, name: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .or_else(|| {
+                self.form
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+            })
+    }
+
+    /// Whether this is a call of API `method`, e.g. `is("SYNO.Foto.Browse.Item", "list")`
+    pub fn is(&self, api: &str, method: &str) -> bool {
+        self.param("api").as_deref() == Some(api) && self.param("method").as_deref() == Some(method)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct FakeResponse {
+    status: StatusCode,
+    body: Vec<u8>,
+}
+
+impl FakeResponse {
+    /// Successful API response with `data`
+    pub fn data<D: Serialize>(data: D) -> Self {
+        Self::body(json!({"success": true, "data": data}).to_string())
+    }
+
+    /// Failed API response with DSM error `code`
+    pub fn error(code: u16) -> Self {
+        Self::body(json!({"success": false, "error": {"code": code}}).to_string())
+    }
+
+    /// Response with raw `body`, e.g. a downloaded file
+    pub fn body<B: Into<Vec<u8>>>(body: B) -> Self {
+        FakeResponse {
+            status: StatusCode::OK,
+            body: body.into(),
+        }
+    }
+}
+
+impl HttpResponse for FakeResponse {
+    fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    async fn text(self) -> Result<String> {
+        Ok(String::from_utf8(self.body)?)
+    }
+
+    async fn json<T: DeserializeOwned + 'static>(self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.body.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(std::mem::take(&mut self.body)))
+    }
+}
+
+type Handler = Box<dyn Fn(&Request) -> FakeResponse>;
+
+/// Responds to requests with `handler`
+pub(crate) struct FakeHttpClient {
+    handler: Handler,
+}
+
+impl FakeHttpClient {
+    pub fn new<H: Fn(&Request) -> FakeResponse + 'static>(handler: H) -> Self {
+        FakeHttpClient {
+            handler: Box::new(handler),
+        }
+    }
+
+    fn respond(&self, request: Request) -> FakeResponse {
+        (self.handler)(&request)
+    }
+}
+
+impl HttpClient for FakeHttpClient {
+    type Response = FakeResponse;
+
+    async fn get<U: IntoUrl>(&self, url: U) -> Result<Self::Response> {
+        Ok(self.respond(Request {
+            url: url.into_url()?,
+            form: vec![],
+        }))
+    }
+
+    async fn post<U: IntoUrl, F: Serialize>(&self, url: U, form: &F) -> Result<Self::Response> {
+        Ok(self.respond(Request {
+            url: url.into_url()?,
+            form: serde_json::from_value(serde_json::to_value(form)?)?,
+        }))
+    }
+}